@@ -10,23 +10,16 @@ struct Context {
 
 impl Default for Context {
     fn default() -> Context {
-        Context {
-            exclusions: vec![
-                MediaType::parse_flexible("application/gzip").unwrap(),
-                MediaType::parse_flexible("application/zip").unwrap(),
-                MediaType::parse_flexible("image/*").unwrap(),
-                MediaType::parse_flexible("video/*").unwrap(),
-                MediaType::parse_flexible("application/wasm").unwrap(),
-                MediaType::parse_flexible("application/octet-stream").unwrap(),
-            ],
-        }
+        Context { exclusions: super::CompressionUtils::default_exclusions() }
     }
 }
 
 /// Compresses all responses with Brotli or Gzip compression.
 ///
 /// Compression is done in the same manner as the [`Compress`](super::Compress)
-/// responder.
+/// responder. In particular, partial responses to range requests, such as
+/// those of a [`NamedFile`](rocket::response::NamedFile), are never
+/// compressed, as their ranges refer to the uncompressed body.
 ///
 /// By default, the fairing does not compress responses with a `Content-Type`
 /// matching any of the following:
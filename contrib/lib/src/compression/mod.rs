@@ -1,7 +1,8 @@
 //! Gzip and Brotli response compression.
 //!
-//! See the [`Compression`](compression::Compression) and
-//! [`Compress`](compression::Compress) types for further details.
+//! See the [`Compression`](compression::Compression),
+//! [`Compress`](compression::Compress), and
+//! [`Compressed`](compression::Compressed) types for further details.
 //!
 //! # Enabling
 //!
@@ -23,15 +24,19 @@
 //! evaluated in the context of your application before enabling compression.
 //!
 
+// The module is also compiled for `serve`, which only negotiates encodings.
+#![cfg_attr(not(any(feature = "brotli_compression", feature = "gzip_compression")),
+    allow(dead_code))]
+
 mod fairing;
 mod responder;
 
 pub use self::fairing::Compression;
-pub use self::responder::Compress;
+pub use self::responder::{Compress, Compressed};
 
 use std::io::Read;
 
-use rocket::http::MediaType;
+use rocket::http::{MediaType, Status};
 use rocket::http::hyper::header::{ContentEncoding, Encoding};
 use rocket::{Request, Response};
 
@@ -41,27 +46,88 @@ use brotli::enc::backward_references::BrotliEncoderMode;
 #[cfg(feature = "gzip_compression")]
 use flate2::read::GzEncoder;
 
-struct CompressionUtils;
+pub(crate) struct CompressionUtils;
 
 impl CompressionUtils {
-    fn accepts_encoding(request: &Request<'_>, encoding: &str) -> bool {
+    /// The media types that are not compressed by default as they are usually
+    /// already compressed.
+    fn default_exclusions() -> Vec<MediaType> {
+        vec![
+            MediaType::parse_flexible("application/gzip").unwrap(),
+            MediaType::parse_flexible("application/zip").unwrap(),
+            MediaType::parse_flexible("image/*").unwrap(),
+            MediaType::parse_flexible("video/*").unwrap(),
+            MediaType::parse_flexible("application/wasm").unwrap(),
+            MediaType::parse_flexible("application/octet-stream").unwrap(),
+        ]
+    }
+
+    /// Returns `true` if the `Accept-Encoding` header of `request` accepts
+    /// `encoding`, that is, lists it with a weight, if any, greater than `0`.
+    pub(crate) fn accepts_encoding(request: &Request<'_>, encoding: &str) -> bool {
         request
             .headers()
             .get("Accept-Encoding")
             .flat_map(|accept| accept.split(','))
-            .map(|accept| accept.trim())
-            .any(|accept| accept == encoding)
+            .map(|coding| coding.split(';').map(str::trim).collect::<Vec<_>>())
+            .any(|params| {
+                params[0].eq_ignore_ascii_case(encoding)
+                    && params[1..].iter().all(|param| {
+                        CompressionUtils::weight(param).map_or(true, |q| q > 0.0)
+                    })
+            })
+    }
+
+    /// Returns the weight given by the parameter `param`, such as `q=0.5`, or
+    /// `None` if `param` isn't a weight. Invalid weights are treated as `0`.
+    fn weight(param: &str) -> Option<f32> {
+        let mut parts = param.splitn(2, '=').map(str::trim);
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if name.eq_ignore_ascii_case("q") => {
+                Some(value.parse().ok().filter(|q: &f32| q.is_finite()).unwrap_or(0.0))
+            }
+            _ => None,
+        }
     }
 
     fn already_encoded(response: &Response<'_>) -> bool {
         response.headers().get("Content-Encoding").next().is_some()
     }
 
+    /// Returns `true` if `response` is a partial response. The ranges of such
+    /// a response refer to the unencoded representation, so compressing its
+    /// body would corrupt them.
+    fn is_partial(response: &Response<'_>) -> bool {
+        response.status() == Status::PartialContent || response.headers().contains("Content-Range")
+    }
+
+    /// Returns `true` if `request` accepts an encoding that is compressed to
+    /// when the corresponding feature is enabled.
+    fn accepts_any_encoding(request: &Request<'_>) -> bool {
+        (cfg!(feature = "brotli_compression") && CompressionUtils::accepts_encoding(request, "br"))
+            || (cfg!(feature = "gzip_compression")
+                && CompressionUtils::accepts_encoding(request, "gzip"))
+    }
+
+    /// Weakens the entity tag of `response`, if it has a strong one. An
+    /// encoded representation differs from the unencoded one, so it can't
+    /// share a strong entity tag with it.
+    fn weaken_etag(response: &mut Response<'_>) {
+        let weak_etag = response.headers().get_one("ETag")
+            .filter(|etag| etag.starts_with('"'))
+            .map(|etag| format!("W/{}", etag));
+
+        if let Some(etag) = weak_etag {
+            response.set_raw_header("ETag", etag);
+        }
+    }
+
     fn set_body_and_encoding<'r, B: Read + 'r>(
         response: &mut Response<'r>,
         body: B,
         encoding: Encoding,
     ) {
+        CompressionUtils::weaken_etag(response);
         response.set_header(ContentEncoding(vec![encoding]));
         response.set_streamed_body(body);
     }
@@ -82,17 +148,20 @@ impl CompressionUtils {
         }
     }
 
+    fn is_compressible(response: &Response<'_>, exclusions: &[MediaType]) -> bool {
+        !CompressionUtils::already_encoded(response)
+            && !CompressionUtils::is_partial(response)
+            && !CompressionUtils::skip_encoding(&response.content_type(), exclusions)
+    }
+
     fn compress_response(request: &Request<'_>, response: &mut Response<'_>, exclusions: &[MediaType]) {
-        if CompressionUtils::already_encoded(response) {
+        // Partial responses are skipped by `is_compressible()`.
+        if !CompressionUtils::is_compressible(response, exclusions) {
             return;
         }
 
         let content_type = response.content_type();
 
-        if CompressionUtils::skip_encoding(&content_type, exclusions) {
-            return;
-        }
-
         // Compression is done when the request accepts brotli or gzip encoding
         // and the corresponding feature is enabled
         if cfg!(feature = "brotli_compression") && CompressionUtils::accepts_encoding(request, "br")
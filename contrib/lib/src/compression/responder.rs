@@ -1,4 +1,5 @@
-use rocket::response::{self, Responder, Response};
+use rocket::response::{self, Body, Responder, Response};
+use rocket::http::MediaType;
 use rocket::Request;
 
 use super::CompressionUtils;
@@ -19,7 +20,11 @@ use super::CompressionUtils;
 /// [flate2](https://github.com/alexcrichton/flate2-rs) crate), quality is set
 /// to the default (9) in order to have good compression ratio.
 ///
-/// Responses that already have a `Content-Encoding` header are not compressed.
+/// Responses that already have a `Content-Encoding` header are not compressed,
+/// nor are partial responses, those with a **206 Partial Content** status or a
+/// `Content-Range` header, whose ranges refer to the uncompressed body. A
+/// strong `ETag` of a compressed response is made weak, as the compressed
+/// body isn't byte-for-byte identical to the uncompressed one.
 ///
 /// # Usage
 ///
@@ -45,3 +50,122 @@ impl<'r, R: Responder<'r>> Responder<'r> for Compress<R> {
         Ok(response)
     }
 }
+
+/// Compresses responses with Brotli or Gzip compression when worthwhile.
+///
+/// Like [`Compress`], `Compressed` compresses the response of the wrapped
+/// `Responder` in accordance with the `Accept-Encoding` header. Unlike
+/// `Compress`, it only does so when compression is likely to be worthwhile.
+/// Responses are not compressed when:
+///
+///   * They already have a `Content-Encoding` header.
+///   * They are partial responses to range requests.
+///   * Their `Content-Type` matches one of the media types excluded by default
+///     by the [`Compression`](super::Compression) fairing, such as `image/*`
+///     and `video/*`, which are usually already compressed.
+///   * Their body has a known size smaller than the minimum size, which
+///     defaults to 1 KiB, or larger than the maximum size, if one is set via
+///     [`Compressed::max_size()`]. Bodies of unknown size are always
+///     compressed.
+///
+/// Responses that may be compressed carry a `Vary: Accept-Encoding` header,
+/// regardless of whether the client accepted a compressed response, so that
+/// caches do not serve a compressed response to clients that can't decode it.
+/// This includes responses without a body, such as a **304 Not Modified**;
+/// as they stand in for the response that would have been compressed, their
+/// `ETag`, if strong, is weakened when the client accepts a compressed
+/// response.
+///
+/// # Usage
+///
+/// Compress responses by wrapping a `Responder` with `Compressed::new()`:
+///
+/// ```rust
+/// use rocket_contrib::compression::Compressed;
+///
+/// # #[allow(unused_variables)]
+/// let response = Compressed::new("Hi.".repeat(1024));
+///
+/// // Only compress bodies of at least 8 KiB.
+/// # #[allow(unused_variables)]
+/// let response = Compressed::new("Hi.".repeat(1024)).min_size(8 * 1024);
+///
+/// // Only compress bodies of between 1 KiB and 64 MiB.
+/// # #[allow(unused_variables)]
+/// let response = Compressed::new("Hi.".repeat(1024)).max_size(64 * 1024 * 1024);
+/// ```
+///
+/// Files served with a [`NamedFile`](rocket::response::NamedFile) have a
+/// body of known size, so that a window of sizes can be used to skip both
+/// tiny files, for which compression isn't worth its overhead, and huge files,
+/// which are better streamed as-is than compressed while responding.
+#[derive(Debug)]
+pub struct Compressed<R> {
+    responder: R,
+    min_size: u64,
+    max_size: Option<u64>,
+    exclusions: Vec<MediaType>,
+}
+
+impl<R> Compressed<R> {
+    /// The default minimum size, in bytes, of bodies to compress.
+    const DEFAULT_MIN_SIZE: u64 = 1024;
+
+    /// Wraps `responder` so that its response is compressed when worthwhile.
+    #[inline]
+    pub fn new(responder: R) -> Compressed<R> {
+        Compressed {
+            responder,
+            min_size: Self::DEFAULT_MIN_SIZE,
+            max_size: None,
+            exclusions: CompressionUtils::default_exclusions(),
+        }
+    }
+
+    /// Sets the minimum size, in bytes, of bodies of known size to compress.
+    #[inline]
+    pub fn min_size(mut self, min_size: u64) -> Compressed<R> {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of bodies of known size to compress.
+    /// By default, there is no maximum size.
+    #[inline]
+    pub fn max_size(mut self, max_size: u64) -> Compressed<R> {
+        self.max_size = Some(max_size);
+        self
+    }
+}
+
+impl<'r, R: Responder<'r>> Responder<'r> for Compressed<R> {
+    fn respond_to(self, request: &Request<'_>) -> response::Result<'r> {
+        let mut response = Response::build()
+            .merge(self.responder.respond_to(request)?)
+            .finalize();
+
+        if !CompressionUtils::is_compressible(&response, &self.exclusions) {
+            return Ok(response);
+        }
+
+        // A response without a body, such as a `304 Not Modified`, describes
+        // the representation that would have been sent, which is compressed
+        // when the client accepts an encoding.
+        let max_size = self.max_size.unwrap_or(u64::max_value());
+        match response.body() {
+            Some(Body::Sized(_, size)) if size < self.min_size || size > max_size => {}
+            Some(_) => {
+                response.adjoin_raw_header("Vary", "Accept-Encoding");
+                CompressionUtils::compress_response(request, &mut response, &self.exclusions);
+            }
+            None => {
+                response.adjoin_raw_header("Vary", "Accept-Encoding");
+                if CompressionUtils::accepts_any_encoding(request) {
+                    CompressionUtils::weaken_etag(&mut response);
+                }
+            }
+        }
+
+        Ok(response)
+    }
+}
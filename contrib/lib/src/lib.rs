@@ -52,5 +52,7 @@
 #[cfg(feature="databases")] pub mod databases;
 #[cfg(feature = "helmet")] pub mod helmet;
 #[cfg(any(feature="brotli_compression", feature="gzip_compression"))] pub mod compression;
+#[cfg(all(feature="serve", not(any(feature="brotli_compression", feature="gzip_compression"))))]
+mod compression;
 
 #[cfg(feature="databases")] #[doc(hidden)] pub use rocket_contrib_codegen::*;
@@ -14,12 +14,21 @@
 //! features = ["serve"]
 //! ```
 
-use std::path::{PathBuf, Path};
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Component, PathBuf, Path};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use rocket::{Request, Data, Route};
-use rocket::http::{Method, uri::Segments};
+use rocket::{Request, Response, Data, Route};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Header, MediaType, Method, Status, uri::{Segments, Uri}};
 use rocket::handler::{Handler, Outcome};
-use rocket::response::NamedFile;
+use rocket::response::{self, content, NamedFile, MemoryFile, MetadataCache, Responder};
+use rocket::response::{CacheDecision, Validators};
+use glob::{Pattern, MatchOptions};
+
+use crate::compression::CompressionUtils;
 
 /// A bitset representing configurable options for the [`StaticFiles`] handler.
 ///
@@ -28,6 +37,8 @@ use rocket::response::NamedFile;
 ///   * [`Options::None`] - Return only present, visible files.
 ///   * [`Options::DotFiles`] - In addition to visible files, return dotfiles.
 ///   * [`Options::Index`] - Render `index.html` pages for directory requests.
+///   * [`Options::JsonListing`] - List directories as JSON for JSON requests.
+///   * [`Options::AutoIndex`] - List directories without an index page as HTML.
 ///
 /// `Options` structures can be `or`d together to select two or more options.
 /// For instance, to request that both dot files and index pages be returned,
@@ -53,6 +64,25 @@ impl Options {
     /// directories beginning with `.`. This is _not_ enabled by default.
     pub const DotFiles: Options = Options(0b0010);
 
+    /// `Options` enabling responding to requests for a directory whose
+    /// `Accept` header prefers `application/json` with a listing of the
+    /// directory's entries. The listing is a JSON array of objects of the form
+    /// `{ "name": .., "size": .., "modified": .., "is_dir": .. }`, where
+    /// `modified` is the number of seconds since the Unix epoch or `null`.
+    /// Dot files are only listed if [`Options::DotFiles`] is enabled. This is
+    /// _not_ enabled by default.
+    pub const JsonListing: Options = Options(0b0100);
+
+    /// `Options` enabling responding to requests for a directory that has no
+    /// `index.html` file, or for any directory if [`Options::Index`] is not
+    /// enabled, with an HTML page listing the directory's entries. The page is
+    /// rendered with [`StaticFiles::autoindex_template()`] or, by default, a
+    /// plain built-in template whose links are relative to the directory, so
+    /// the directory should be requested with a trailing slash. Dot files are
+    /// only listed if [`Options::DotFiles`] is enabled. This is _not_ enabled
+    /// by default.
+    pub const AutoIndex: Options = Options(0b1000);
+
     /// Returns `true` if `self` is a superset of `other`. In other words,
     /// returns `true` if all of the options in `other` are also in `self`.
     ///
@@ -94,6 +124,525 @@ impl std::ops::BitOr for Options {
     }
 }
 
+/// How the [`StaticFiles`] handler serves compressed files.
+///
+/// The mode is set with [`StaticFiles::compression()`]. By default, it is
+/// [`CompressionMode::Off`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionMode {
+    /// Files are served as they are.
+    Off,
+    /// A precompressed sibling of a file, `${file}.br` or `${file}.gz`, is
+    /// served in place of the file when the request's `Accept-Encoding`
+    /// header accepts its encoding, preferring Brotli over gzip. The response
+    /// has the `Content-Type` of the file and the `Content-Encoding` of the
+    /// sibling. Only files with a recognized extension have siblings.
+    Precompressed,
+    /// As [`CompressionMode::Precompressed`], but a file without a sibling is
+    /// compressed on the fly as by [`Compressed`](crate::compression::Compressed)
+    /// when it is compressible: when its type isn't usually already compressed
+    /// and it is at least 1 KiB in size. Byte range requests are never
+    /// compressed on the fly. This requires the `gzip_compression` or
+    /// `brotli_compression` feature; without either, this is the same as
+    /// [`CompressionMode::Precompressed`].
+    Auto,
+}
+
+impl Default for CompressionMode {
+    fn default() -> Self {
+        CompressionMode::Off
+    }
+}
+
+/// A `Cache-Control` policy for files served by the [`StaticFiles`] handler.
+///
+/// A policy is set for all files with [`StaticFiles::cache_control()`] and for
+/// files matching a glob with [`StaticFiles::cache_rule()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheControl(Cow<'static, str>);
+
+impl CacheControl {
+    /// Returns a policy with the raw `Cache-Control` header value
+    /// `directives`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::CacheControl;
+    ///
+    /// let policy = CacheControl::new("private, max-age=60");
+    /// assert_eq!(policy.directives(), "private, max-age=60");
+    /// ```
+    pub fn new<S: Into<Cow<'static, str>>>(directives: S) -> CacheControl {
+        CacheControl(directives.into())
+    }
+
+    /// Returns a policy allowing caches to store a file for a year without
+    /// revalidating it: `immutable, max-age=31536000`. This is suitable for
+    /// files whose name changes whenever their contents do.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::CacheControl;
+    ///
+    /// assert_eq!(CacheControl::immutable().directives(), "immutable, max-age=31536000");
+    /// ```
+    pub fn immutable() -> CacheControl {
+        CacheControl::new("immutable, max-age=31536000")
+    }
+
+    /// Returns a policy requiring caches to revalidate a file before each use:
+    /// `no-cache`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::CacheControl;
+    ///
+    /// assert_eq!(CacheControl::no_cache().directives(), "no-cache");
+    /// ```
+    pub fn no_cache() -> CacheControl {
+        CacheControl::new("no-cache")
+    }
+
+    /// Returns a policy allowing caches to use a file for `seconds` seconds
+    /// without revalidating it: `max-age=<seconds>`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::CacheControl;
+    ///
+    /// assert_eq!(CacheControl::max_age(3600).directives(), "max-age=3600");
+    /// ```
+    pub fn max_age(seconds: u64) -> CacheControl {
+        CacheControl::new(format!("max-age={}", seconds))
+    }
+
+    /// Returns the `Cache-Control` header value of this policy.
+    #[inline(always)]
+    pub fn directives(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A fairing that applies a default `Cache-Control` policy to file responses.
+///
+/// The policy is applied to every response generated by a file responder, such
+/// as [`NamedFile`] or [`MemoryFile`], which is detected through the request's
+/// [`CacheDecision`], unless the response already has a `Cache-Control` header.
+/// This includes responses from any route, not only those of [`StaticFiles`].
+/// Only **200 OK**, **206 Partial Content**, and **304 Not Modified** responses
+/// are changed: responses to requests whose preconditions failed and files
+/// sent with another status, such as a custom **404 Not Found** page, are left
+/// unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rocket;
+/// # extern crate rocket_contrib;
+/// use rocket_contrib::serve::{DefaultCacheControl, CacheControl};
+///
+/// fn main() {
+/// # if false {
+///     rocket::ignite()
+///         .attach(DefaultCacheControl::new(CacheControl::max_age(3600)))
+///         .launch();
+/// # }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DefaultCacheControl(CacheControl);
+
+impl DefaultCacheControl {
+    /// Returns a fairing applying `policy` to file responses without a
+    /// `Cache-Control` header.
+    pub fn new(policy: CacheControl) -> DefaultCacheControl {
+        DefaultCacheControl(policy)
+    }
+}
+
+impl Fairing for DefaultCacheControl {
+    fn info(&self) -> Info {
+        Info { name: "Default Cache-Control", kind: Kind::Response }
+    }
+
+    fn on_response(&self, request: &Request<'_>, response: &mut Response<'_>) {
+        let status = response.status();
+        if status != Status::Ok && status != Status::PartialContent
+            && status != Status::NotModified
+        {
+            return;
+        }
+
+        match CacheDecision::of(request) {
+            Some(CacheDecision::Sent) | Some(CacheDecision::NotModified) => {
+                if !response.headers().contains("Cache-Control") {
+                    response.set_raw_header("Cache-Control", self.0.directives().to_string());
+                }
+            }
+            Some(CacheDecision::PreconditionFailed) | None => {}
+        }
+    }
+}
+
+/// A CORS policy for files served by the [`StaticFiles`] handler.
+///
+/// A policy is set with [`StaticFiles::cors()`]. It determines the origins
+/// allowed to load files cross-origin and, optionally, how long browsers may
+/// cache the result of a preflight request. The allowed methods are always
+/// `GET`, `HEAD`, and `OPTIONS`.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rocket_contrib;
+/// use rocket_contrib::serve::CorsPolicy;
+///
+/// let any = CorsPolicy::any_origin().max_age(86400);
+/// let some = CorsPolicy::origins(vec!["https://rocket.rs", "https://api.rocket.rs"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsPolicy {
+    /// The allowed origins or `None` if any origin is allowed.
+    origins: Option<Vec<String>>,
+    max_age: Option<u64>,
+}
+
+impl CorsPolicy {
+    /// Returns a policy allowing requests from any origin. Responses carry an
+    /// `Access-Control-Allow-Origin: *` header.
+    pub fn any_origin() -> CorsPolicy {
+        CorsPolicy { origins: None, max_age: None }
+    }
+
+    /// Returns a policy allowing requests from the origins in `origins`, such
+    /// as `https://rocket.rs`. Responses to requests from an allowed origin
+    /// carry that origin in the `Access-Control-Allow-Origin` header; other
+    /// responses carry no CORS headers. All responses carry a `Vary: Origin`
+    /// header.
+    pub fn origins<I, S>(origins: I) -> CorsPolicy
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        let origins = origins.into_iter().map(Into::into).collect();
+        CorsPolicy { origins: Some(origins), max_age: None }
+    }
+
+    /// Allows browsers to cache the result of a preflight request for
+    /// `seconds` seconds via the `Access-Control-Max-Age` header.
+    pub fn max_age(mut self, seconds: u64) -> CorsPolicy {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Returns the CORS headers for a response to `req`, including those for
+    /// a preflight request if `preflight` is `true`.
+    fn headers(&self, req: &Request<'_>, preflight: bool) -> Vec<Header<'static>> {
+        let origin = match &self.origins {
+            None => Some("*".to_string()),
+            Some(origins) => req.headers().get_one("Origin")
+                .filter(|origin| origins.iter().any(|allowed| allowed == origin))
+                .map(|origin| origin.to_string())
+        };
+
+        let mut headers = vec![];
+        if self.origins.is_some() {
+            headers.push(Header::new("Vary", "Origin"));
+        }
+
+        if let Some(origin) = origin {
+            headers.push(Header::new("Access-Control-Allow-Origin", origin));
+            if preflight {
+                headers.push(Header::new("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS"));
+                if let Some(max_age) = self.max_age {
+                    headers.push(Header::new("Access-Control-Max-Age", max_age.to_string()));
+                }
+            }
+        }
+
+        headers
+    }
+}
+
+/// A source of files for the [`StaticFiles`] handler.
+///
+/// By default, `StaticFiles` serves files from directories on the local file
+/// system; `FileProvider` is implemented for [`PathBuf`] to this end. To serve
+/// files from another source, such as assets embedded in the binary, implement
+/// `FileProvider` and use [`StaticFiles::from_provider()`].
+pub trait FileProvider: Send + Sync + 'static {
+    /// Returns the file at `path`, which is relative to the root of the
+    /// provider, or `None` if there is no such file. Directories are not files:
+    /// requests for them are handled by opening their `index.html` file when
+    /// [`Options::Index`] is enabled.
+    fn open(&self, path: &Path) -> Option<VirtualFile>;
+
+    /// Returns the entries of the directory at `path`, which is relative to
+    /// the root of the provider, or `None` if there is no such directory. This
+    /// is used to respond with a listing when [`Options::JsonListing`] or
+    /// [`Options::AutoIndex`] is enabled. The default implementation returns
+    /// `None`.
+    fn list(&self, _path: &Path) -> Option<Vec<DirEntry>> {
+        None
+    }
+
+    /// Returns the names of at most `limit` entries of the directory at
+    /// `path`, in no particular order, or `None` if there is no such
+    /// directory. Implementations should stop reading the directory once
+    /// `limit` entries are found. This is used to match paths
+    /// case-insensitively when [`StaticFiles::case_insensitive()`] is
+    /// enabled. The default implementation returns the names of the first
+    /// `limit` entries returned by [`FileProvider::list()`].
+    fn list_names(&self, path: &Path, limit: usize) -> Option<Vec<String>> {
+        let entries = self.list(path)?;
+        Some(entries.into_iter().take(limit).map(|entry| entry.name).collect())
+    }
+
+    /// Returns the location on the file system of the file at `path`, which
+    /// is relative to the root of the provider, without querying the file
+    /// system, or `None` if the provider doesn't serve files from the file
+    /// system. This is used to answer conditional requests from the index
+    /// kept by [`StaticFiles::cache_index()`]. The default implementation
+    /// returns `None`.
+    fn locate(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Serves files in the directory `self`.
+impl FileProvider for PathBuf {
+    fn open(&self, path: &Path) -> Option<VirtualFile> {
+        let path = self.join(path);
+        if path.is_dir() {
+            return None;
+        }
+
+        NamedFile::open(path).ok().map(VirtualFile::from)
+    }
+
+    fn list(&self, path: &Path) -> Option<Vec<DirEntry>> {
+        let mut entries: Vec<_> = fs::read_dir(self.join(path)).ok()?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some(DirEntry {
+                    name: entry.file_name().into_string().ok()?,
+                    size: if metadata.is_dir() { 0 } else { metadata.len() },
+                    modified: metadata.modified().ok(),
+                    is_dir: metadata.is_dir(),
+                })
+            })
+            .collect();
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Some(entries)
+    }
+
+    fn list_names(&self, path: &Path, limit: usize) -> Option<Vec<String>> {
+        let names = fs::read_dir(self.join(path)).ok()?
+            .take(limit)
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+
+        Some(names)
+    }
+
+    fn locate(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.join(path))
+    }
+}
+
+/// An entry in a directory listing returned by [`FileProvider::list()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirEntry {
+    /// The name of the entry.
+    pub name: String,
+    /// The size of the entry in bytes. This is `0` for directories.
+    pub size: u64,
+    /// The last modification time of the entry, if it is known.
+    pub modified: Option<SystemTime>,
+    /// Whether the entry is a directory.
+    pub is_dir: bool,
+}
+
+/// Returns the JSON array listing `entries`, omitting dot files unless
+/// `dotfiles` is `true`.
+fn json_listing(entries: &[DirEntry], dotfiles: bool) -> String {
+    let entries: Vec<_> = entries.iter()
+        .filter(|entry| dotfiles || !entry.name.starts_with('.'))
+        .map(|entry| {
+            let modified = entry.modified
+                .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map_or("null".to_string(), |duration| duration.as_secs().to_string());
+
+            format!("{{\"name\":\"{}\",\"size\":{},\"modified\":{},\"is_dir\":{}}}",
+                json_escape(&entry.name), entry.size, modified, entry.is_dir)
+        })
+        .collect();
+
+    format!("[{}]", entries.join(","))
+}
+
+/// Returns the built-in HTML page listing `entries`.
+fn autoindex(entries: &[DirEntry]) -> String {
+    let items: Vec<_> = entries.iter()
+        .map(|entry| {
+            let slash = if entry.is_dir { "/" } else { "" };
+            let href = Uri::percent_encode(&entry.name);
+            let name = html_escape(&entry.name);
+            format!("<li><a href=\"./{}{}\">{}{}</a></li>", href, slash, name, slash)
+        })
+        .collect();
+
+    format!("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index</title></head>\n\
+        <body>\n<ul>\n{}\n</ul>\n</body>\n</html>\n", items.join("\n"))
+}
+
+/// Escapes `string` for use in HTML text or a quoted attribute value.
+fn html_escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+/// Escapes `string` for use in a JSON string literal.
+fn json_escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+/// A file returned by a [`FileProvider`]: either a [`NamedFile`] on disk or a
+/// [`MemoryFile`] held in memory.
+///
+/// Both kinds of files respond with a `Content-Type` based on the extension of
+/// their path and respond to conditional and byte range requests as a
+/// [`NamedFile`] does. The former requires a modification time for files in
+/// memory.
+#[derive(Debug)]
+pub struct VirtualFile(FileKind);
+
+#[derive(Debug)]
+enum FileKind {
+    Disk(NamedFile),
+    Memory(MemoryFile),
+}
+
+impl VirtualFile {
+    /// Returns a file with contents `data` named `path` and, if `modified` is
+    /// `Some`, last modified at that time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use std::time::SystemTime;
+    /// use rocket_contrib::serve::VirtualFile;
+    ///
+    /// let file = VirtualFile::from_memory("app.css", &b"body {}"[..], Some(SystemTime::now()));
+    /// ```
+    pub fn from_memory<P, D>(path: P, data: D, modified: Option<SystemTime>) -> VirtualFile
+        where P: AsRef<Path>, D: Into<Cow<'static, [u8]>>
+    {
+        let file = MemoryFile::new(path, data);
+        VirtualFile::from(match modified {
+            Some(time) => file.modified(time),
+            None => file
+        })
+    }
+}
+
+impl VirtualFile {
+    /// Returns the validators of the file, if they are known.
+    fn validators(&self) -> Option<Validators> {
+        match &self.0 {
+            FileKind::Disk(file) => file.validators(),
+            FileKind::Memory(file) => file.validators().cloned(),
+        }
+    }
+
+    /// Retrieves the metadata of a file on disk from `cache`.
+    fn cached(self, cache: &MetadataCache) -> Option<VirtualFile> {
+        match self.0 {
+            FileKind::Disk(file) => file.cached(cache).ok().map(VirtualFile::from),
+            FileKind::Memory(_) => Some(self),
+        }
+    }
+
+    /// Returns the size of the file in bytes if it is a file in memory or a
+    /// regular file on disk.
+    fn len(&self) -> Option<u64> {
+        match &self.0 {
+            FileKind::Disk(file) => file.metadata().ok()
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len()),
+            FileKind::Memory(file) => Some(file.data().len() as u64),
+        }
+    }
+
+    /// Sets whether byte range requests for the file are honored.
+    fn accept_ranges(self, enabled: bool) -> VirtualFile {
+        match self.0 {
+            FileKind::Disk(file) => file.accept_ranges(enabled).into(),
+            FileKind::Memory(file) => file.accept_ranges(enabled).into(),
+        }
+    }
+
+    /// Replaces the validators of the file with `validators`.
+    fn with_validators(self, validators: Validators) -> VirtualFile {
+        match self.0 {
+            FileKind::Disk(file) => file.with_validators(validators).into(),
+            FileKind::Memory(file) => file.with_validators(validators).into(),
+        }
+    }
+}
+
+impl From<NamedFile> for VirtualFile {
+    fn from(file: NamedFile) -> VirtualFile {
+        VirtualFile(FileKind::Disk(file))
+    }
+}
+
+impl From<MemoryFile> for VirtualFile {
+    fn from(file: MemoryFile) -> VirtualFile {
+        VirtualFile(FileKind::Memory(file))
+    }
+}
+
+impl Responder<'_> for VirtualFile {
+    fn respond_to(self, req: &Request<'_>) -> response::Result<'static> {
+        match self.0 {
+            FileKind::Disk(file) => file.respond_to(req),
+            FileKind::Memory(file) => file.respond_to(req),
+        }
+    }
+}
+
 /// Custom handler for serving static files.
 ///
 /// This handler makes it simple to serve static files from a directory on the
@@ -105,6 +654,16 @@ impl std::ops::BitOr for Options {
 /// routes is `10`. To customize route ranking, use the [`StaticFiles::rank()`]
 /// method.
 ///
+/// Routes are generated for `GET` and `OPTIONS` requests; `HEAD` requests are
+/// handled by the `GET` routes. An `OPTIONS` request for a file that exists
+/// receives a **204 No Content** response with an `Allow: GET, HEAD, OPTIONS`
+/// header.
+///
+/// Files, including the `index.html` files served for directories, are served
+/// as a [`NamedFile`] is: responses carry `ETag` and `Last-Modified` headers
+/// and conditional requests such as `If-None-Match` and `If-Modified-Since`
+/// receive a **304 Not Modified** response when the file hasn't changed.
+///
 /// # Options
 ///
 /// The handler's functionality can be customized by passing an [`Options`] to
@@ -157,15 +716,36 @@ impl std::ops::BitOr for Options {
 /// ```
 #[derive(Clone)]
 pub struct StaticFiles {
-    root: PathBuf,
+    providers: Vec<Arc<dyn FileProvider>>,
     options: Options,
     rank: isize,
+    prefix: Option<PathBuf>,
+    rewrite: Option<Arc<dyn Fn(&Path) -> Option<PathBuf> + Send + Sync>>,
+    cache_rules: Vec<(Pattern, CacheControl)>,
+    cache_control: Option<CacheControl>,
+    fingerprinted: bool,
+    cors: Option<CorsPolicy>,
+    index_dependencies: Vec<PathBuf>,
+    index: Option<Arc<MetadataCache>>,
+    compression: CompressionMode,
+    exact_length: bool,
+    case_insensitive: bool,
+    image_negotiation: bool,
+    range_types: Option<Vec<ContentType>>,
+    autoindex: Option<Arc<dyn Fn(&[DirEntry]) -> String + Send + Sync>>,
 }
 
 impl StaticFiles {
     /// The default rank use by `StaticFiles` routes.
     const DEFAULT_RANK: isize = 10;
 
+    /// The number of files whose metadata is kept by [`StaticFiles::cache_index()`].
+    const INDEX_CAPACITY: usize = 4096;
+
+    /// The maximum number of entries in a directory searched for a match by
+    /// [`StaticFiles::case_insensitive()`].
+    const CASE_INSENSITIVE_LIMIT: usize = 1024;
+
     /// Constructs a new `StaticFiles` that serves files from the file system
     /// `path`. By default, [`Options::Index`] is set, and the generated routes
     /// have a rank of `10`. To serve static files with other options, use
@@ -237,7 +817,136 @@ impl StaticFiles {
     /// }
     /// ```
     pub fn new<P: AsRef<Path>>(path: P, options: Options) -> Self {
-        StaticFiles { root: path.as_ref().into(), options, rank: Self::DEFAULT_RANK }
+        StaticFiles::with_providers(vec![Arc::new(path.as_ref().to_path_buf())], options)
+    }
+
+    /// Constructs a new `StaticFiles` that serves files from the file system
+    /// directories in `paths`, overlaid in order: a request is handled with
+    /// the file from the first directory that contains it. Directory listings
+    /// combine the entries of all directories, with those of earlier ones
+    /// taking precedence. By default, [`Options::Index`] is set, and the
+    /// generated routes have a rank of `10`. To set options, use
+    /// [`StaticFiles::options()`].
+    ///
+    /// # Example
+    ///
+    /// Serve static files from `/www/override`, falling back to `/www/public`
+    /// for files that aren't overridden:
+    ///
+    /// ```rust
+    /// # extern crate rocket;
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// fn main() {
+    /// # if false {
+    ///     rocket::ignite()
+    ///         .mount("/", StaticFiles::overlay(vec!["/www/override", "/www/public"]))
+    ///         .launch();
+    /// # }
+    /// }
+    /// ```
+    pub fn overlay<I, P>(paths: I) -> Self
+        where I: IntoIterator<Item = P>, P: AsRef<Path>
+    {
+        let providers = paths.into_iter()
+            .map(|path| Arc::new(path.as_ref().to_path_buf()) as Arc<dyn FileProvider>)
+            .collect();
+
+        StaticFiles::with_providers(providers, Options::default())
+    }
+
+    /// Constructs a new `StaticFiles` that serves files from `provider`
+    /// instead of the local file system. By default, [`Options::Index`] is
+    /// set, and the generated routes have a rank of `10`. To set options, use
+    /// [`StaticFiles::options()`].
+    ///
+    /// # Example
+    ///
+    /// Serve a stylesheet embedded in the binary at `/app.css`:
+    ///
+    /// ```rust
+    /// # extern crate rocket;
+    /// # extern crate rocket_contrib;
+    /// use std::path::Path;
+    /// use rocket_contrib::serve::{StaticFiles, FileProvider, VirtualFile};
+    ///
+    /// struct Embedded;
+    ///
+    /// impl FileProvider for Embedded {
+    ///     fn open(&self, path: &Path) -> Option<VirtualFile> {
+    ///         match path.to_str() {
+    ///             Some("app.css") => Some(VirtualFile::from_memory(path, &b"body {}"[..], None)),
+    ///             _ => None
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    /// # if false {
+    ///     rocket::ignite()
+    ///         .mount("/", StaticFiles::from_provider(Embedded))
+    ///         .launch();
+    /// # }
+    /// }
+    /// ```
+    pub fn from_provider<F: FileProvider>(provider: F) -> Self {
+        StaticFiles::with_providers(vec![Arc::new(provider)], Options::default())
+    }
+
+    fn with_providers(providers: Vec<Arc<dyn FileProvider>>, options: Options) -> Self {
+        StaticFiles {
+            providers,
+            options,
+            rank: Self::DEFAULT_RANK,
+            prefix: None,
+            rewrite: None,
+            cache_rules: vec![],
+            cache_control: None,
+            fingerprinted: false,
+            cors: None,
+            index_dependencies: vec![],
+            index: None,
+            compression: CompressionMode::default(),
+            exact_length: false,
+            case_insensitive: false,
+            image_negotiation: false,
+            range_types: None,
+            autoindex: None,
+        }
+    }
+
+    /// Sets the options for the handler to `options`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::{StaticFiles, Options};
+    ///
+    /// StaticFiles::overlay(vec!["/override", "/public"]).options(Options::DotFiles);
+    /// ```
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Disables serving `index.html` files in response to requests for
+    /// directories, so that such requests are forwarded. This removes
+    /// [`Options::Index`] from the handler's options, leaving the others
+    /// intact. Requests for an `index.html` file by name are still served.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/assets").index_disabled();
+    /// ```
+    pub fn index_disabled(mut self) -> Self {
+        self.options = Options(self.options.0 & !Options::Index.0);
+        self
     }
 
     /// Sets the rank for generated routes to `rank`.
@@ -258,51 +967,850 @@ impl StaticFiles {
         self.rank = rank;
         self
     }
+
+    /// Strips `prefix` from the requested path before looking up the file in
+    /// the file system. Requests for paths that do not begin with `prefix` are
+    /// forwarded.
+    ///
+    /// This decouples the path at which files are requested from the layout of
+    /// the directory they are served from.
+    ///
+    /// # Example
+    ///
+    /// Serve the file `/www/public/app.js` for requests to `/assets/app.js`
+    /// even though the handler is mounted at `/`:
+    ///
+    /// ```rust
+    /// # extern crate rocket;
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// fn main() {
+    /// # if false {
+    ///     rocket::ignite()
+    ///         .mount("/", StaticFiles::from("/www/public").strip_prefix("/assets"))
+    ///         .launch();
+    /// # }
+    /// }
+    /// ```
+    pub fn strip_prefix<P: AsRef<Path>>(mut self, prefix: P) -> Self {
+        let prefix = prefix.as_ref();
+        self.prefix = Some(prefix.strip_prefix("/").unwrap_or(prefix).into());
+        self
+    }
+
+    /// Rewrites requested paths using `f` before looking up the file in the
+    /// file system.
+    ///
+    /// The function `f` is called with the requested path relative to the
+    /// mount point, after stripping any prefix set via
+    /// [`StaticFiles::strip_prefix()`]. It returns the path, relative to the
+    /// root directory, of the file or directory to serve instead. If `f`
+    /// returns `None`, the request is forwarded.
+    ///
+    /// The returned path is checked like a requested path: requests whose
+    /// rewritten path is absolute or contains `.` or `..` segments, which
+    /// could escape the root directory, are forwarded, as are those whose
+    /// rewritten path contains dot files unless [`Options::DotFiles`] is
+    /// enabled.
+    ///
+    /// # Example
+    ///
+    /// Serve versioned assets from the `builds` directory, so that a request
+    /// for `/v2/app.js` is handled with the file `/www/public/builds/v2/app.js`:
+    ///
+    /// ```rust
+    /// # extern crate rocket;
+    /// # extern crate rocket_contrib;
+    /// use std::path::Path;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// fn main() {
+    /// # if false {
+    ///     let files = StaticFiles::from("/www/public")
+    ///         .rewrite(|path| Some(Path::new("builds").join(path)));
+    ///
+    ///     rocket::ignite()
+    ///         .mount("/", files)
+    ///         .launch();
+    /// # }
+    /// }
+    /// ```
+    pub fn rewrite<F>(mut self, f: F) -> Self
+        where F: Fn(&Path) -> Option<PathBuf> + Send + Sync + 'static
+    {
+        self.rewrite = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets the `Cache-Control` policy for files that don't match any rule
+    /// added via [`StaticFiles::cache_rule()`] to `policy`. By default, no
+    /// `Cache-Control` header is sent.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::{StaticFiles, CacheControl};
+    ///
+    /// StaticFiles::from("/public").cache_control(CacheControl::no_cache());
+    /// ```
+    pub fn cache_control(mut self, policy: CacheControl) -> Self {
+        self.cache_control = Some(policy);
+        self
+    }
+
+    /// Adds a rule applying the `Cache-Control` policy `policy` to files
+    /// matching `glob`. Rules are tried in the order they were added; the
+    /// first matching rule applies. Files matching no rule receive the policy
+    /// set via [`StaticFiles::cache_control()`], if any.
+    ///
+    /// If `glob` contains a `/`, it is matched against the path of the file
+    /// relative to the root directory, and wildcards don't match `/`.
+    /// Otherwise, it is matched against the file name alone. See
+    /// [`glob::Pattern`] for the glob syntax.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `glob` is not a valid glob.
+    ///
+    /// # Example
+    ///
+    /// Cache hashed bundles like `app.3f9a2b.js` for a year while requiring
+    /// every other file to be revalidated:
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::{StaticFiles, CacheControl};
+    ///
+    /// StaticFiles::from("/public")
+    ///     .cache_rule("*.[0-9a-f]*.js", CacheControl::immutable())
+    ///     .cache_control(CacheControl::no_cache());
+    /// ```
+    pub fn cache_rule(mut self, glob: &str, policy: CacheControl) -> Self {
+        let pattern = Pattern::new(glob)
+            .unwrap_or_else(|e| panic!("invalid cache rule glob '{}': {}", glob, e));
+
+        self.cache_rules.push((pattern, policy));
+        self
+    }
+
+    /// Attaches CORS headers according to `policy` to responses for files
+    /// served by this handler, allowing them to be loaded cross-origin. CORS
+    /// preflight requests, `OPTIONS` requests for existing files, receive a
+    /// **204 No Content** response with the appropriate headers. By default,
+    /// no CORS headers are sent.
+    ///
+    /// The policy only applies to the routes of this handler, not to any other
+    /// routes of the application.
+    ///
+    /// # Example
+    ///
+    /// Allow fonts in `/www/fonts` to be loaded by pages on any origin:
+    ///
+    /// ```rust
+    /// # extern crate rocket;
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::{StaticFiles, CorsPolicy};
+    ///
+    /// fn main() {
+    /// # if false {
+    ///     let fonts = StaticFiles::from("/www/fonts").cors(CorsPolicy::any_origin());
+    ///     rocket::ignite()
+    ///         .mount("/fonts", fonts)
+    ///         .launch();
+    /// # }
+    /// }
+    /// ```
+    pub fn cors(mut self, policy: CorsPolicy) -> Self {
+        self.cors = Some(policy);
+        self
+    }
+
+    /// Keeps an in-memory index of the size and validators of served files so
+    /// that requests are answered without querying the file system for the
+    /// file's metadata. Conditional requests for indexed files that result in
+    /// a **304 Not Modified** or **412 Precondition Failed** response are
+    /// answered from the index alone, without opening the file; this requires
+    /// providers that implement [`FileProvider::locate()`], as directories on
+    /// the file system do. Entries are refreshed once they are older than
+    /// `ttl`, or when the file is opened and its length has changed; until
+    /// then, changes to a file are not reflected in its `ETag` or
+    /// `Last-Modified`. The index holds the metadata of up to 4096 files,
+    /// evicting the least recently used ones. See [`MetadataCache`] for
+    /// details.
+    ///
+    /// Responses whose file or validators depend on more than the requested
+    /// file, those for `index.html` files with dependencies set via
+    /// [`StaticFiles::index_dependencies()`] or for files served with image
+    /// negotiation or compression enabled, are always answered from the file
+    /// system.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use std::time::Duration;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/public").cache_index(Duration::from_secs(30));
+    /// ```
+    pub fn cache_index(self, ttl: Duration) -> Self {
+        self.index(MetadataCache::new(Self::INDEX_CAPACITY).ttl(ttl))
+    }
+
+    /// Keeps the index of the size and validators of served files in `cache`,
+    /// as [`StaticFiles::cache_index()`] does with a cache of its own. This
+    /// allows the index to be configured as any [`MetadataCache`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket;
+    /// # extern crate rocket_contrib;
+    /// use std::time::Duration;
+    /// use rocket::response::MetadataCache;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// let index = MetadataCache::new(256).ttl(Duration::from_secs(30));
+    /// StaticFiles::from("/www/public").index(index);
+    /// ```
+    pub fn index(mut self, cache: MetadataCache) -> Self {
+        self.index = Some(Arc::new(cache));
+        self
+    }
+
+    /// Returns the indexed validators of the file at `path`, if the response
+    /// for it can be answered from the index alone. Providers that don't
+    /// locate their files on the file system can't be checked for the file,
+    /// so only entries of providers preceding the first such provider apply.
+    fn indexed_validators(&self, path: &Path) -> Option<Validators> {
+        let index = self.index.as_ref()?;
+        let has_dependencies = !self.index_dependencies.is_empty()
+            && path.file_name() == Some("index.html".as_ref());
+
+        if has_dependencies || self.image_negotiation || self.compression != CompressionMode::Off {
+            return None;
+        }
+
+        // Only the entry of the provider that serves the file applies. An
+        // earlier provider serves it if it has it, even if a later provider's
+        // entry is indexed, so those without an entry must lack the file.
+        for provider in &self.providers {
+            let location = provider.locate(path)?;
+            match index.validators(&location) {
+                Some(validators) => return Some(validators),
+                None if location.exists() => return None,
+                None => continue
+            }
+        }
+
+        None
+    }
+
+    /// Derives the `Last-Modified` and `ETag` headers of `index.html` files
+    /// from the files at `paths` in addition to the index file itself.
+    ///
+    /// The last modification time of an index file is then the latest of those
+    /// of the index file and the files at `paths`, and its entity tag changes
+    /// whenever any of theirs does. This allows an application shell such as a
+    /// single-page application's `index.html` to be revalidated whenever one
+    /// of the assets it references changes. The `paths` are file system paths;
+    /// those that don't exist are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/public")
+    ///     .index_dependencies(vec!["/www/public/app.js", "/www/public/app.css"]);
+    /// ```
+    pub fn index_dependencies<I, P>(mut self, paths: I) -> Self
+        where I: IntoIterator<Item = P>, P: AsRef<Path>
+    {
+        self.index_dependencies = paths.into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+
+        self
+    }
+
+    /// Returns `file`, served from `path`, with its validators derived from
+    /// the index dependencies if it is an index file.
+    fn apply_index_dependencies(&self, path: &Path, file: VirtualFile) -> VirtualFile {
+        if self.index_dependencies.is_empty() || path.file_name() != Some("index.html".as_ref()) {
+            return file;
+        }
+
+        let dependencies = self.index_dependencies.iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .filter_map(|metadata| Validators::from_metadata(&metadata));
+
+        match Validators::combine(file.validators().into_iter().chain(dependencies)) {
+            Some(validators) => file.with_validators(validators),
+            None => file
+        }
+    }
+
+    /// Applies a `Cache-Control` policy to files based on whether their name
+    /// contains a content hash. Files with a fingerprinted name, such as
+    /// `main.4f3a2b.js` or `main-4f3a2b.js`, receive `public, max-age=31536000,
+    /// immutable`. Other files receive the policy set via
+    /// [`StaticFiles::cache_control()`] or, if there is none, `no-cache`.
+    /// Rules added via [`StaticFiles::cache_rule()`] take precedence.
+    ///
+    /// A name is considered fingerprinted if, apart from its first part and
+    /// its extension, it has a `.` or `-` separated part of at least six
+    /// hexadecimal digits, at least one of which is a decimal digit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/dist").immutable_if_fingerprinted();
+    /// ```
+    pub fn immutable_if_fingerprinted(mut self) -> Self {
+        self.fingerprinted = true;
+        self
+    }
+
+    /// Sets how compressed files are served to `mode`. See [`CompressionMode`]
+    /// for details. By default, files are served as they are.
+    ///
+    /// With any mode other than [`CompressionMode::Off`], responses carry a
+    /// `Vary: Accept-Encoding` header.
+    ///
+    /// # Example
+    ///
+    /// Serve `/www/dist/app.js.br` or `/www/dist/app.js.gz` in place of
+    /// `/www/dist/app.js` to clients that accept them, compressing other
+    /// files on the fly:
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::{StaticFiles, CompressionMode};
+    ///
+    /// StaticFiles::from("/www/dist").compression(CompressionMode::Auto);
+    /// ```
+    pub fn compression(mut self, mode: CompressionMode) -> Self {
+        self.compression = mode;
+        self
+    }
+
+    /// Ensures that responses for files of known size, files in memory and
+    /// regular files on disk, carry their exact length. Such files are never
+    /// compressed on the fly, which would require chunked transfer encoding,
+    /// so their responses always carry a `Content-Length`. Additionally, the
+    /// size of the entire file is sent in an `X-Content-Length` header, which
+    /// some download managers use to report progress, even for partial
+    /// responses.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/downloads").exact_length();
+    /// ```
+    pub fn exact_length(mut self) -> Self {
+        self.exact_length = true;
+        self
+    }
+
+    /// Matches requested paths case-insensitively when there is no file at
+    /// the exact path. Each segment of the path is then matched against the
+    /// entries of its parent directory, preferring an exact match, so that a
+    /// request for `/Logo.PNG` is handled with the file `logo.png`. If several
+    /// entries match a segment, the first in lexicographic order is used.
+    ///
+    /// To bound the cost of a miss, directories with more than 1024 entries
+    /// are not searched, and reading a directory stops once that many entries
+    /// are found. This requires providers that implement
+    /// [`FileProvider::list_names()`] or [`FileProvider::list()`], as
+    /// directories on the file system do.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/public").case_insensitive();
+    /// ```
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+
+    /// Returns the path of the entry matching `path` case-insensitively, if
+    /// there is one.
+    fn case_insensitive_path(&self, path: &Path) -> Option<PathBuf> {
+        let mut resolved = PathBuf::new();
+        for segment in path.iter() {
+            let segment = segment.to_str()?;
+            // Reading one entry more than the limit reveals larger directories.
+            let limit = Self::CASE_INSENSITIVE_LIMIT + 1;
+            let mut names = vec![];
+            for provider in &self.providers {
+                let remaining = limit - names.len();
+                names.extend(provider.list_names(&resolved, remaining).into_iter().flatten());
+                if names.len() >= limit {
+                    return None;
+                }
+            }
+
+            names.sort();
+            let lowercase = segment.to_lowercase();
+            let name = names.iter()
+                .find(|name| name.as_str() == segment)
+                .or_else(|| names.iter().find(|name| name.to_lowercase() == lowercase))?;
+
+            resolved.push(name);
+        }
+
+        Some(resolved)
+    }
+
+    /// Returns `true` if a provider that locates its files on the file system
+    /// has an entry, such as a directory, at the exact path `path`. A path
+    /// that exists isn't matched case-insensitively even if it can't be
+    /// opened as a file.
+    fn exists(&self, path: &Path) -> bool {
+        self.providers.iter()
+            .filter_map(|provider| provider.locate(path))
+            .any(|location| location.exists())
+    }
+
+    /// Serves images in a more efficient format when the client accepts it.
+    /// A request for an image, such as `/hero.jpg`, whose `Accept` header
+    /// explicitly accepts `image/avif` or `image/webp` is handled with the
+    /// sibling in that format, such as `/hero.avif` or `/hero.webp`, if it
+    /// exists, preferring AVIF over WebP. Otherwise, the requested image is
+    /// served. Wildcards such as `image/*` don't select a sibling.
+    ///
+    /// Responses for images carry a `Vary: Accept` header.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/images").image_negotiation();
+    /// ```
+    pub fn image_negotiation(mut self) -> Self {
+        self.image_negotiation = true;
+        self
+    }
+
+    /// Honors byte range requests only for files whose `Content-Type`, as
+    /// implied by their extension, is one of `types`. Parameters of the media
+    /// types are ignored. Responses for other files carry an `Accept-Ranges:
+    /// none` header, and their `Range` headers are ignored, so that the
+    /// entire file is sent. By default, range requests are honored for all
+    /// files of known size.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket;
+    /// # extern crate rocket_contrib;
+    /// use rocket::http::ContentType;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/media").ranges_for(&[ContentType::MP4, ContentType::WEBM]);
+    /// ```
+    pub fn ranges_for(mut self, types: &[ContentType]) -> Self {
+        self.range_types = Some(types.to_vec());
+        self
+    }
+
+    /// Renders the HTML listings of directories with `template`, which is
+    /// called with the entries of the directory, excluding dot files unless
+    /// [`Options::DotFiles`] is enabled, and returns the page to send. This
+    /// enables [`Options::AutoIndex`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rocket_contrib;
+    /// use rocket_contrib::serve::StaticFiles;
+    ///
+    /// StaticFiles::from("/www/public").autoindex_template(|entries| {
+    ///     let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    ///     format!("<p class=\"listing\">{}</p>", names.join(", "))
+    /// });
+    /// ```
+    pub fn autoindex_template<F>(mut self, template: F) -> Self
+        where F: Fn(&[DirEntry]) -> String + Send + Sync + 'static
+    {
+        self.options = self.options | Options::AutoIndex;
+        self.autoindex = Some(Arc::new(template));
+        self
+    }
+
+    /// Returns `true` if byte range requests are honored for the file at
+    /// `path`.
+    fn accepts_ranges(&self, path: &Path) -> bool {
+        let types = match &self.range_types {
+            Some(types) => types,
+            None => return true
+        };
+
+        content_type(path).map_or(false, |ct| {
+            types.iter().any(|t| t.top() == ct.top() && t.sub() == ct.sub())
+        })
+    }
+
+    /// Returns the path, file, and media type of the sibling of the image at
+    /// `path` in the most efficient format accepted by `req`, if any.
+    fn image_variant(
+        &self,
+        req: &Request<'_>,
+        path: &Path
+    ) -> Option<(PathBuf, VirtualFile, &'static str)> {
+        let accept = req.accept()?;
+        [("avif", "image/avif"), ("webp", "image/webp")].iter()
+            .filter(|&&(extension, _)| path.extension() != Some(extension.as_ref()))
+            .filter(|&&(extension, _)| accept.iter().any(|accepted| {
+                let media_type = accepted.media_type();
+                accepted.weight_or(1.0) > 0.0
+                    && media_type.top() == "image"
+                    && media_type.sub() == extension
+            }))
+            .filter_map(|&(extension, media_type)| {
+                let variant = path.with_extension(extension);
+                let file = self.providers.iter().filter_map(|p| p.open(&variant)).next();
+                file.map(|file| (variant, file, media_type))
+            })
+            .next()
+    }
+
+    /// Returns the precompressed sibling of the file at `path` accepted by
+    /// `req`, if any, along with its encoding.
+    fn precompressed(&self, req: &Request<'_>, path: &Path) -> Option<(VirtualFile, &'static str)> {
+        if self.compression == CompressionMode::Off || content_type(path).is_none() {
+            return None;
+        }
+
+        [("br", "br"), ("gzip", "gz")].iter()
+            .filter(|(encoding, _)| CompressionUtils::accepts_encoding(req, encoding))
+            .filter_map(|&(encoding, extension)| {
+                let mut sibling = path.as_os_str().to_os_string();
+                sibling.push(".");
+                sibling.push(extension);
+
+                let sibling = Path::new(&sibling);
+                let file = self.providers.iter().filter_map(|p| p.open(sibling)).next();
+                file.map(|file| (file, encoding))
+            })
+            .next()
+    }
+
+    /// Returns the entries of the directory at `path` across all providers,
+    /// or `None` if no provider has such a directory. An entry of an earlier
+    /// provider overrides any entry with the same name of a later one.
+    fn list(&self, path: &Path) -> Option<Vec<DirEntry>> {
+        let mut listings = self.providers.iter().filter_map(|p| p.list(path));
+        let mut entries = listings.next()?;
+        for listing in listings {
+            for entry in listing {
+                if !entries.iter().any(|existing| existing.name == entry.name) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Some(entries)
+    }
+
+    /// Returns the `Cache-Control` policy for the file at `path`, relative to
+    /// the root directory.
+    fn cache_policy(&self, path: &Path) -> Option<CacheControl> {
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        let name = path.file_name().map(Path::new).unwrap_or(path);
+        let rule = self.cache_rules.iter()
+            .find(|(pattern, _)| match pattern.as_str().contains('/') {
+                true => pattern.matches_path_with(path, options),
+                false => pattern.matches_path_with(name, options)
+            })
+            .map(|(_, policy)| policy.clone());
+
+        if rule.is_some() || !self.fingerprinted {
+            return rule.or_else(|| self.cache_control.clone());
+        }
+
+        match is_fingerprinted(&name.to_string_lossy()) {
+            true => Some(CacheControl::new("public, max-age=31536000, immutable")),
+            false => self.cache_control.clone().or_else(|| Some(CacheControl::no_cache()))
+        }
+    }
+}
+
+/// Returns `true` if the file name `name` contains a content hash: a part,
+/// other than the first and the extension, of at least six hexadecimal digits
+/// including a decimal digit.
+fn is_fingerprinted(name: &str) -> bool {
+    let stem = match name.rfind('.') {
+        Some(i) if i > 0 => &name[..i],
+        _ => return false
+    };
+
+    stem.split(|c| c == '.' || c == '-')
+        .skip(1)
+        .any(|part| part.len() >= 6
+            && part.chars().all(|c| c.is_ascii_hexdigit())
+            && part.chars().any(|c| c.is_ascii_digit()))
+}
+
+/// Returns `true` if `path` consists only of normal components, and thus can't
+/// escape the directory it is joined to, and, unless `dotfiles` is `true`, of
+/// no components starting with `.`.
+fn is_contained(path: &Path, dotfiles: bool) -> bool {
+    path.components().all(|component| match component {
+        Component::Normal(name) => dotfiles || !name.to_string_lossy().starts_with('.'),
+        _ => false
+    })
+}
+
+/// Returns the `Content-Type` implied by the extension of `path`, if any.
+fn content_type(path: &Path) -> Option<ContentType> {
+    path.extension().and_then(|ext| ContentType::from_extension(&ext.to_string_lossy()))
+}
+
+/// Adds `field` to the `Vary` header in `headers`, adding the header if there
+/// is none.
+fn vary(headers: &mut Vec<Header<'static>>, field: &str) {
+    match headers.iter().position(|header| header.name() == "Vary") {
+        Some(i) => {
+            let value = format!("{}, {}", headers[i].value(), field);
+            headers[i] = Header::new("Vary", value);
+        }
+        None => headers.push(Header::new("Vary", field.to_string()))
+    }
 }
 
 impl Into<Vec<Route>> for StaticFiles {
     fn into(self) -> Vec<Route> {
-        let non_index = Route::ranked(self.rank, Method::Get, "/<path..>", self.clone());
-        if self.options.contains(Options::Index) {
-            let index = Route::ranked(self.rank, Method::Get, "/", self);
-            vec![index, non_index]
-        } else {
-            vec![non_index]
+        let mut routes = vec![];
+        let serves_directories = self.options.contains(Options::Index)
+            || self.options.contains(Options::JsonListing)
+            || self.options.contains(Options::AutoIndex);
+
+        for &method in &[Method::Get, Method::Options] {
+            if serves_directories {
+                routes.push(Route::ranked(self.rank, method, "/", self.clone()));
+            }
+
+            routes.push(Route::ranked(self.rank, method, "/<path..>", self.clone()));
         }
+
+        routes
     }
 }
 
 impl Handler for StaticFiles {
     fn handle<'r>(&self, req: &'r Request<'_>, data: Data) -> Outcome<'r> {
-        fn handle_dir<'r>(opt: Options, r: &'r Request<'_>, d: Data, path: &Path) -> Outcome<'r> {
-            if !opt.contains(Options::Index) {
-                return Outcome::forward(d);
+        // If this is not the route with segments, the mount point itself was
+        // requested. This route only exists if the user requested handling of
+        // index files. Otherwise, we're handling segments. Get the segments as
+        // a `PathBuf`, only allowing dotfiles if the user allowed it.
+        let current_route = req.route().expect("route while handling");
+        let is_segments_route = current_route.uri.path().ends_with(">");
+        let allow_dotfiles = self.options.contains(Options::DotFiles);
+        let path = match is_segments_route {
+            true => req.get_segments::<Segments<'_>>(0)
+                .and_then(|res| res.ok())
+                .and_then(|segments| segments.into_path_buf(allow_dotfiles).ok()),
+            false => Some(PathBuf::new())
+        };
+
+        // Strip the user's prefix, if any, forwarding requests outside of it,
+        // and then apply the user's rewrite, if any.
+        let path = path
+            .and_then(|path| match &self.prefix {
+                Some(prefix) => path.strip_prefix(prefix).ok().map(|p| p.to_path_buf()),
+                None => Some(path)
+            })
+            .and_then(|path| match &self.rewrite {
+                Some(rewrite) => rewrite(&path).filter(|path| is_contained(path, allow_dotfiles)),
+                None => Some(path)
+            });
+
+        // Preflight and discovery requests only need to know that the file
+        // exists and which methods it supports.
+        let preflight = req.method() == Method::Options;
+        let mut headers = self.cors.as_ref()
+            .map(|policy| policy.headers(req, preflight))
+            .unwrap_or_default();
+
+        // Programmatic clients may ask for a listing of a directory.
+        let prefers_json = req.accept()
+            .map_or(false, |accept| accept.preferred().media_type() == &MediaType::JSON);
+
+        if self.options.contains(Options::JsonListing) && !preflight && prefers_json {
+            let entries = path.as_ref().and_then(|path| self.list(path));
+
+            if let Some(entries) = entries {
+                vary(&mut headers, "Accept");
+                let listing = json_listing(&entries, allow_dotfiles);
+                return Outcome::from(req, WithHeaders(content::Json(listing), headers));
             }
+        }
+
+        // Conditional requests for indexed files may be answered without
+        // querying the file system at all.
+        let indexed = path.as_ref()
+            .filter(|_| !preflight)
+            .and_then(|path| Some((path, self.indexed_validators(path)?)))
+            .filter(|(_, validators)| validators.check(req).is_some());
 
-            let file = NamedFile::open(path.join("index.html")).ok();
-            Outcome::from_or_forward(r, d, file)
+        if let Some((path, validators)) = indexed {
+            if let Some(policy) = self.cache_policy(path) {
+                headers.push(Header::new("Cache-Control", policy.0));
+            }
+
+            let file = MemoryFile::new(path, &b""[..]).with_validators(validators);
+            return Outcome::from(req, WithHeaders(file, headers));
         }
 
-        // If this is not the route with segments, handle it only if the user
-        // requested a handling of index files.
-        let current_route = req.route().expect("route while handling");
-        let is_segments_route = current_route.uri.path().ends_with(">");
-        if !is_segments_route {
-            return handle_dir(self.options, req, data, &self.root);
+        // Serve the file from the first provider that has it. If there is no
+        // such file, the path may be a directory: try its index file.
+        let open_exact = |path: PathBuf| {
+            let file = self.providers.iter().filter_map(|p| p.open(&path)).next();
+            file.map(|file| (path, file))
+        };
+
+        let open = |path: PathBuf| match open_exact(path.clone()) {
+            Some(file) => Some(file),
+            None if self.case_insensitive && !self.exists(&path) => {
+                open_exact(self.case_insensitive_path(&path)?)
+            }
+            None => None
+        };
+
+        let file = path.clone().and_then(|path| {
+            open(path.clone()).or_else(|| match self.options.contains(Options::Index) {
+                true => open(path.join("index.html")),
+                false => None
+            })
+        });
+
+        // A directory without an index file may be listed instead.
+        if file.is_none() && self.options.contains(Options::AutoIndex) && !preflight {
+            let entries = path.as_ref().and_then(|path| self.list(path));
+
+            if let Some(mut entries) = entries {
+                entries.retain(|entry| allow_dotfiles || !entry.name.starts_with('.'));
+                let page = match &self.autoindex {
+                    Some(template) => template(&entries),
+                    None => autoindex(&entries)
+                };
+
+                return Outcome::from(req, WithHeaders(content::Html(page), headers));
+            }
         }
 
-        // Otherwise, we're handling segments. Get the segments as a `PathBuf`,
-        // only allowing dotfiles if the user allowed it.
-        let allow_dotfiles = self.options.contains(Options::DotFiles);
-        let path = req.get_segments::<Segments<'_>>(0)
-            .and_then(|res| res.ok())
-            .and_then(|segments| segments.into_path_buf(allow_dotfiles).ok())
-            .map(|path| self.root.join(path));
-
-        match &path {
-            Some(path) if path.is_dir() => handle_dir(self.options, req, data, path),
-            Some(path) => Outcome::from_or_forward(req, data, NamedFile::open(path).ok()),
+        if preflight {
+            let allow = file.map(|_| {
+                headers.push(Header::new("Allow", "GET, HEAD, OPTIONS"));
+                WithHeaders(Status::NoContent, headers)
+            });
+
+            return Outcome::from_or_forward(req, data, allow);
+        }
+
+        let file = file.and_then(|(path, file)| match &self.index {
+            Some(index) => file.cached(index).map(|file| (path, file)),
+            None => Some((path, file))
+        });
+
+        // Serve an image in a more efficient format if there is one.
+        let is_image = |path: &Path| content_type(path).map_or(false, |ct| ct.top() == "image");
+        let file = match file {
+            Some((path, file)) if self.image_negotiation && is_image(&path) => {
+                vary(&mut headers, "Accept");
+                match self.image_variant(req, &path) {
+                    Some((path, file, media_type)) => {
+                        headers.push(Header::new("Content-Type", media_type));
+                        Some((path, file))
+                    }
+                    None => Some((path, file))
+                }
+            }
+            file => file
+        };
+
+        // Serve a precompressed sibling in place of the file if there is one.
+        let file = file.map(|(path, file)| match self.precompressed(req, &path) {
+            Some((sibling, encoding)) => (path, sibling, Some(encoding)),
+            None => (path, file, None)
+        });
+
+        let file = file.map(|(path, file, encoding)| {
+            let mut file = self.apply_index_dependencies(&path, file);
+            if !self.accepts_ranges(&path) {
+                file = file.accept_ranges(false);
+            }
+
+            if let Some(policy) = self.cache_policy(&path) {
+                headers.push(Header::new("Cache-Control", policy.0));
+            }
+
+            if self.compression != CompressionMode::Off {
+                vary(&mut headers, "Accept-Encoding");
+            }
+
+            if let Some(encoding) = encoding {
+                headers.push(Header::new("Content-Encoding", encoding));
+                headers.extend(content_type(&path).map(Into::into));
+            }
+
+            let len = match self.exact_length {
+                true => file.len(),
+                false => None
+            };
+
+            if let Some(len) = len {
+                headers.push(Header::new("X-Content-Length", len.to_string()));
+            }
+
+            (WithHeaders(file, headers), encoding.is_none() && len.is_none())
+        });
+
+        // Files without a sibling may be compressed on the fly unless their
+        // exact length is required. The ranges of a partial response refer to
+        // the uncompressed file, so those are never compressed.
+        match file {
+            #[cfg(any(feature = "brotli_compression", feature = "gzip_compression"))]
+            Some((file, true))
+                if self.compression == CompressionMode::Auto && !req.headers().contains("Range") =>
+            {
+                Outcome::from(req, crate::compression::Compressed::new(file))
+            }
+            Some((file, _)) => Outcome::from(req, file),
             None => Outcome::forward(data)
         }
     }
 }
+
+/// A responder that sets additional headers on the response of the wrapped
+/// responder, overriding any existing values.
+struct WithHeaders<R>(R, Vec<Header<'static>>);
+
+impl<'r, R: Responder<'r>> Responder<'r> for WithHeaders<R> {
+    fn respond_to(self, req: &Request<'_>) -> response::Result<'r> {
+        let mut response = self.0.respond_to(req)?;
+        for header in self.1 {
+            response.set_header(header);
+        }
+
+        Ok(response)
+    }
+}
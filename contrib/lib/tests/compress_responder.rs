@@ -237,4 +237,228 @@ mod compress_responder_tests {
             String::from(HELLO)
         );
     }
+
+    mod compressed {
+        use super::*;
+
+        use rocket_contrib::compression::Compressed;
+
+        #[get("/large")]
+        fn large() -> Compressed<String> {
+            Compressed::new(HELLO.repeat(20))
+        }
+
+        #[get("/small")]
+        fn small() -> Compressed<String> {
+            Compressed::new(String::from(HELLO))
+        }
+
+        #[get("/small_threshold")]
+        fn small_threshold() -> Compressed<String> {
+            Compressed::new(String::from(HELLO)).min_size(16)
+        }
+
+        #[get("/jpeg")]
+        fn jpeg() -> Compressed<Content<String>> {
+            Compressed::new(Content(ContentType::JPEG, HELLO.repeat(20)))
+        }
+
+        fn client() -> Client {
+            let routes = routes![large, small, small_threshold, jpeg];
+            Client::new(rocket::ignite().mount("/", routes)).expect("valid rocket instance")
+        }
+
+        fn get(client: &Client, path: &'static str) -> rocket::local::LocalResponse<'_> {
+            client.get(path)
+                .header(Header::new("Accept-Encoding", "gzip"))
+                .dispatch()
+        }
+
+        fn gunzip(bytes: Vec<u8>) -> String {
+            let mut body_plain = String::new();
+            GzDecoder::new(&bytes[..])
+                .read_to_string(&mut body_plain)
+                .expect("decompress response");
+
+            body_plain
+        }
+
+        #[test]
+        fn test_compresses_large_text() {
+            let client = client();
+            let mut response = get(&client, "/large");
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+            assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+            assert_eq!(gunzip(response.body_bytes().unwrap()), HELLO.repeat(20));
+        }
+
+        #[test]
+        fn test_sets_vary_without_accept_encoding() {
+            let client = client();
+            let mut response = client.get("/large").dispatch();
+            assert!(response.headers().get_one("Content-Encoding").is_none());
+            assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+            assert_eq!(response.body_string(), Some(HELLO.repeat(20)));
+        }
+
+        #[test]
+        fn test_honors_encoding_weights() {
+            let client = client();
+            let mut response = client.get("/large")
+                .header(Header::new("Accept-Encoding", "br;q=0, gzip;q=0.5"))
+                .dispatch();
+
+            assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+            assert_eq!(gunzip(response.body_bytes().unwrap()), HELLO.repeat(20));
+
+            let mut response = client.get("/large")
+                .header(Header::new("Accept-Encoding", "br;q=0.0, gzip; q=0.000"))
+                .dispatch();
+
+            assert!(response.headers().get_one("Content-Encoding").is_none());
+            assert_eq!(response.body_string(), Some(HELLO.repeat(20)));
+        }
+
+        #[test]
+        fn test_skips_jpeg() {
+            let client = client();
+            let mut response = get(&client, "/jpeg");
+            assert_eq!(response.status(), Status::Ok);
+            assert!(response.headers().get_one("Content-Encoding").is_none());
+            assert!(response.headers().get_one("Vary").is_none());
+            assert_eq!(response.body_string(), Some(HELLO.repeat(20)));
+        }
+
+        #[test]
+        fn test_respects_min_size() {
+            let client = client();
+            let mut response = get(&client, "/small");
+            assert!(response.headers().get_one("Content-Encoding").is_none());
+            assert_eq!(response.body_string(), Some(String::from(HELLO)));
+
+            let mut response = get(&client, "/small_threshold");
+            assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+            assert_eq!(gunzip(response.body_bytes().unwrap()), HELLO);
+        }
+
+        #[test]
+        fn test_respects_size_window_for_files() {
+            use std::fs::{self, File};
+            use rocket::response::{Body, NamedFile};
+
+            let root = std::env::temp_dir().join("rocket-compressed-window");
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join("tiny.txt"), "0123456789").unwrap();
+            fs::write(root.join("text.txt"), HELLO.repeat(50 * 1024 / HELLO.len() + 1)).unwrap();
+
+            // A sparse file: its size is known without writing its contents.
+            File::create(root.join("huge.txt")).unwrap().set_len(500 * 1024 * 1024).unwrap();
+
+            #[get("/<name>")]
+            fn file(name: String) -> Option<Compressed<NamedFile>> {
+                let path = std::env::temp_dir().join("rocket-compressed-window").join(name);
+                NamedFile::open(path).ok().map(|file| Compressed::new(file).max_size(1 << 20))
+            }
+
+            let rocket = rocket::ignite().mount("/", routes![file]);
+            let client = Client::new(rocket).expect("valid rocket instance");
+
+            let mut response = get(&client, "/tiny.txt");
+            assert!(response.headers().get_one("Content-Encoding").is_none());
+            assert_eq!(response.body_string(), Some("0123456789".into()));
+
+            let mut response = get(&client, "/text.txt");
+            let text = fs::read_to_string(root.join("text.txt")).unwrap();
+            assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+            assert_eq!(gunzip(response.body_bytes().unwrap()), text);
+
+            let mut response = get(&client, "/huge.txt");
+            assert_eq!(response.status(), Status::Ok);
+            assert!(response.headers().get_one("Content-Encoding").is_none());
+            match response.body() {
+                Some(Body::Sized(_, size)) => assert_eq!(size, 500 * 1024 * 1024),
+                _ => panic!("expected a sized, uncompressed body"),
+            }
+
+            fs::remove_dir_all(&root).unwrap();
+        }
+
+        #[test]
+        fn test_skips_partial_responses() {
+            use std::time::{Duration, UNIX_EPOCH};
+            use rocket::response::MemoryFile;
+
+            #[get("/dated.txt")]
+            fn dated() -> Compressed<MemoryFile> {
+                let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+                Compressed::new(MemoryFile::new("dated.txt", HELLO.repeat(20).into_bytes())
+                    .modified(modified))
+            }
+
+            let client = Client::new(rocket::ignite().mount("/", routes![dated]))
+                .expect("valid rocket instance");
+
+            // The entire file is compressed, and its entity tag is weakened.
+            let identity = client.get("/dated.txt").dispatch();
+            let etag = identity.headers().get_one("ETag").unwrap().to_string();
+            assert!(etag.starts_with('"'));
+
+            let mut response = get(&client, "/dated.txt");
+            assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+            assert_eq!(response.headers().get_one("ETag"), Some(&*format!("W/{}", etag)));
+            assert_eq!(gunzip(response.body_bytes().unwrap()), HELLO.repeat(20));
+
+            // A range of the file is sent as-is.
+            let mut response = client.get("/dated.txt")
+                .header(Header::new("Accept-Encoding", "gzip, br"))
+                .header(Header::new("Range", "bytes=0-3"))
+                .dispatch();
+
+            assert_eq!(response.status(), Status::PartialContent);
+            assert!(response.headers().get_one("Content-Encoding").is_none());
+            assert_eq!(response.headers().get_one("Content-Range"),
+                Some(&*format!("bytes 0-3/{}", HELLO.len() * 20)));
+            assert_eq!(response.headers().get_one("ETag"), Some(&*etag));
+            assert_eq!(response.body_string(), Some(HELLO[..4].to_string()));
+        }
+
+        #[test]
+        fn test_not_modified_matches_compressed_response() {
+            use std::time::{Duration, UNIX_EPOCH};
+            use rocket::response::MemoryFile;
+
+            #[get("/dated.txt")]
+            fn dated() -> Compressed<MemoryFile> {
+                let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+                Compressed::new(MemoryFile::new("dated.txt", HELLO.repeat(20).into_bytes())
+                    .modified(modified))
+            }
+
+            let client = Client::new(rocket::ignite().mount("/", routes![dated]))
+                .expect("valid rocket instance");
+
+            let response = get(&client, "/dated.txt");
+            let etag = response.headers().get_one("ETag").unwrap().to_string();
+            assert!(etag.starts_with("W/"));
+
+            let response = client.get("/dated.txt")
+                .header(Header::new("Accept-Encoding", "gzip"))
+                .header(Header::new("If-None-Match", etag.clone()))
+                .dispatch();
+
+            assert_eq!(response.status(), Status::NotModified);
+            assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+            assert_eq!(response.headers().get_one("ETag"), Some(&*etag));
+
+            // Without an accepted encoding, the strong entity tag is kept.
+            let response = client.get("/dated.txt")
+                .header(Header::new("If-None-Match", etag.clone()))
+                .dispatch();
+
+            assert_eq!(response.status(), Status::NotModified);
+            assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+            assert_eq!(response.headers().get_one("ETag"), Some(&etag[2..]));
+        }
+    }
 }
@@ -292,4 +292,40 @@ mod compression_fairing_tests {
             String::from(HELLO)
         );
     }
+
+    #[test]
+    fn test_does_not_compress_partial_responses() {
+        use rocket::response::MemoryFile;
+
+        #[get("/digits.txt")]
+        fn digits() -> MemoryFile {
+            MemoryFile::new("digits.txt", &b"0123456789"[..])
+        }
+
+        let rocket = rocket::ignite()
+            .mount("/", routes![digits])
+            .attach(Compression::fairing());
+
+        let client = Client::new(rocket).expect("valid rocket instance");
+        let mut response = client
+            .get("/digits.txt")
+            .header(Header::new("Accept-Encoding", "deflate, gzip, br"))
+            .header(Header::new("Range", "bytes=2-4"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+        assert_eq!(response.headers().get_one("Content-Range"), Some("bytes 2-4/10"));
+        assert_eq!(response.body_string(), Some(String::from("234")));
+
+        let mut response = client
+            .get("/digits.txt")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .header(Header::new("Range", "bytes=0-1,4-5"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+        assert!(response.body_string().unwrap().contains("Content-Range: bytes 4-5/10"));
+    }
 }
@@ -143,4 +143,1044 @@ mod static_tests {
         assert_all(&client, "both", HIDDEN_FILES, true);
         assert_all(&client, "both", INDEXED_DIRECTORIES, true);
     }
+
+    #[test]
+    fn test_strip_prefix() {
+        let rocket = rocket::ignite()
+            .mount("/", StaticFiles::from(static_root()).strip_prefix("/assets"));
+
+        let client = Client::new(rocket).expect("valid rocket");
+        assert_all(&client, "assets", REGULAR_FILES, true);
+        assert_all(&client, "assets", HIDDEN_FILES, false);
+        assert_all(&client, "assets", INDEXED_DIRECTORIES, true);
+
+        let response = client.get("/inner/goodbye").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.get("/").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let files = StaticFiles::from(static_root())
+            .rewrite(|path| match path.starts_with("v2") {
+                true => Some(Path::new("builds").join(path)),
+                false => None
+            });
+
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+        let mut response = client.get("/v2/app.js").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let mut file = File::open(static_root().join("builds/v2/app.js")).expect("open file");
+        let mut expected_contents = String::new();
+        file.read_to_string(&mut expected_contents).expect("read file");
+        assert_eq!(response.body_string(), Some(expected_contents));
+
+        let response = client.get("/builds/v2/app.js").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.get("/v1/app.js").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_rewrite_cannot_escape_root() {
+        let files = StaticFiles::from(static_root())
+            .rewrite(|path| match path.to_str() {
+                Some("parent") => Some("../static_override/override.txt".into()),
+                Some("nested") => Some("other/../../static_override/override.txt".into()),
+                Some("absolute") => Some(static_root().join("other/hello.txt")),
+                Some("dotfile") => Some("inner/.hideme".into()),
+                _ => Some(path.into())
+            });
+
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+        for path in &["/parent", "/nested", "/absolute", "/dotfile"] {
+            let response = client.get(*path).dispatch();
+            assert_eq!(response.status(), Status::NotFound, "{}", path);
+        }
+
+        let response = client.get("/other/hello.txt").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_overlay() {
+        let override_root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("static_override");
+
+        let files = StaticFiles::overlay(vec![override_root, static_root()]);
+        let rocket = rocket::ignite().mount("/overlay", files);
+        let client = Client::new(rocket).expect("valid rocket");
+
+        let mut response = client.get("/overlay/other/hello.txt").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("Hello from the override!\n".into()));
+
+        let mut response = client.get("/overlay/override.txt").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("Only in the override.\n".into()));
+
+        let base_only: Vec<_> = REGULAR_FILES.iter()
+            .filter(|path| **path != "other/hello.txt")
+            .cloned()
+            .collect();
+
+        assert_all(&client, "overlay", &base_only, true);
+        assert_all(&client, "overlay", HIDDEN_FILES, false);
+        assert_all(&client, "overlay", INDEXED_DIRECTORIES, true);
+    }
+
+    #[test]
+    fn test_memory_provider() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use rocket::http::{ContentType, Header};
+        use rocket_contrib::serve::{FileProvider, VirtualFile};
+
+        struct Embedded;
+
+        impl FileProvider for Embedded {
+            fn open(&self, path: &Path) -> Option<VirtualFile> {
+                let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+                match path.to_str() {
+                    Some("app.css") => Some(VirtualFile::from_memory(path, &b"body {}"[..], Some(modified))),
+                    _ => None
+                }
+            }
+        }
+
+        let rocket = rocket::ignite().mount("/", StaticFiles::from_provider(Embedded));
+        let client = Client::new(rocket).expect("valid rocket");
+
+        let mut response = client.get("/app.css").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::CSS));
+        assert_eq!(response.headers().get_one("Last-Modified"), Some("Sun, 09 Sep 2001 01:46:40 GMT"));
+        assert_eq!(response.body_string(), Some("body {}".into()));
+
+        let response = client.get("/app.css")
+            .header(Header::new("If-Modified-Since", "Sun, 09 Sep 2001 01:46:40 GMT"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        let response = client.get("/app.js").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_conditional_requests() {
+        use rocket::http::Header;
+
+        let client = Client::new(rocket()).expect("valid rocket");
+        let response = client.get("/default/other/hello.txt").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let etag = response.headers().get_one("ETag").expect("etag").to_string();
+
+        let mut response = client.get("/default/other/hello.txt")
+            .header(Header::new("If-None-Match", etag))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+        assert!(response.body_bytes().is_none());
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        use rocket::http::Header;
+
+        let contents = std::fs::read_to_string(static_root().join("other/hello.txt")).unwrap();
+        let len = contents.len();
+
+        let client = Client::new(rocket()).expect("valid rocket");
+        let response = client.get("/default/other/hello.txt").dispatch();
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("bytes"));
+        let etag = response.headers().get_one("ETag").expect("etag").to_string();
+
+        let mut response = client.get("/default/other/hello.txt")
+            .header(Header::new("Range", "bytes=0-1"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        let content_range = format!("bytes 0-1/{}", len);
+        assert_eq!(response.headers().get_one("Content-Range"), Some(&*content_range));
+        assert_eq!(response.body_string(), Some(contents[..2].into()));
+
+        let mut response = client.get("/default/other/hello.txt")
+            .header(Header::new("Range", "bytes=-3"))
+            .header(Header::new("If-Range", etag))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.body_string(), Some(contents[len - 3..].into()));
+
+        let mut response = client.get("/default/other/hello.txt")
+            .header(Header::new("Range", "bytes=0-1"))
+            .header(Header::new("If-Range", "\"stale\""))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some(contents.clone()));
+
+        let response = client.get("/default/other/hello.txt")
+            .header(Header::new("Range", format!("bytes={}-", len)))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::RangeNotSatisfiable);
+        let content_range = format!("bytes */{}", len);
+        assert_eq!(response.headers().get_one("Content-Range"), Some(&*content_range));
+    }
+
+    #[test]
+    fn test_cache_rules() {
+        use rocket_contrib::serve::CacheControl;
+
+        let files = StaticFiles::from(static_root())
+            .cache_rule("*.[0-9a-f]*.js", CacheControl::immutable())
+            .cache_rule("other/*", CacheControl::max_age(60))
+            .cache_control(CacheControl::no_cache());
+
+        let rocket = rocket::ignite()
+            .mount("/", files)
+            .mount("/plain", StaticFiles::from(static_root()).rank(5));
+
+        let client = Client::new(rocket).expect("valid rocket");
+        let cache_control = |path: &str| {
+            let response = client.get(path).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            response.headers().get_one("Cache-Control").map(|s| s.to_string())
+        };
+
+        let immutable = Some("immutable, max-age=31536000".to_string());
+        let no_cache = Some("no-cache".to_string());
+        assert_eq!(cache_control("/builds/v3/app.3f9a2b.js"), immutable);
+        assert_eq!(cache_control("/builds/v2/app.js"), no_cache);
+        assert_eq!(cache_control("/other/hello.txt"), Some("max-age=60".into()));
+        assert_eq!(cache_control("/index.html"), no_cache);
+        assert_eq!(cache_control("/"), no_cache);
+        assert_eq!(cache_control("/plain/index.html"), None);
+    }
+
+    #[test]
+    fn test_options() {
+        let client = Client::new(rocket()).expect("valid rocket");
+        for path in &["/default/other/hello.txt", "/default/", "/no_index/inner/goodbye"] {
+            let mut response = client.options(*path).dispatch();
+            assert_eq!(response.status(), Status::NoContent);
+            assert_eq!(response.headers().get_one("Allow"), Some("GET, HEAD, OPTIONS"));
+            assert!(response.body_bytes().is_none());
+        }
+
+        for path in &["/default/other/missing.txt", "/no_index/", "/default/.hidden"] {
+            let response = client.options(*path).dispatch();
+            assert_eq!(response.status(), Status::NotFound);
+        }
+    }
+
+    #[test]
+    fn test_cors() {
+        use rocket::http::Header;
+        use rocket_contrib::serve::CorsPolicy;
+
+        let root = static_root();
+        let allowed = CorsPolicy::origins(vec!["https://rocket.rs"]);
+        let rocket = rocket::ignite()
+            .mount("/any", StaticFiles::from(&root).cors(CorsPolicy::any_origin().max_age(600)))
+            .mount("/some", StaticFiles::from(&root).cors(allowed))
+            .mount("/none", StaticFiles::from(&root));
+
+        let client = Client::new(rocket).expect("valid rocket");
+        let origin = || Header::new("Origin", "https://rocket.rs");
+
+        let response = client.get("/any/fonts/icons.woff2").header(origin()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), Some("*"));
+        assert_eq!(response.headers().get_one("Access-Control-Max-Age"), None);
+
+        let response = client.options("/any/fonts/icons.woff2")
+            .header(origin())
+            .header(Header::new("Access-Control-Request-Method", "GET"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NoContent);
+        let headers = response.headers();
+        assert_eq!(headers.get_one("Access-Control-Allow-Origin"), Some("*"));
+        assert_eq!(headers.get_one("Access-Control-Allow-Methods"), Some("GET, HEAD, OPTIONS"));
+        assert_eq!(headers.get_one("Access-Control-Max-Age"), Some("600"));
+
+        let response = client.get("/some/fonts/icons.woff2").header(origin()).dispatch();
+        let headers = response.headers();
+        assert_eq!(headers.get_one("Access-Control-Allow-Origin"), Some("https://rocket.rs"));
+        assert_eq!(headers.get_one("Vary"), Some("Origin"));
+
+        let response = client.get("/some/fonts/icons.woff2")
+            .header(Header::new("Origin", "https://example.com"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+
+        let response = client.get("/none/fonts/icons.woff2").header(origin()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+
+        let response = client.options("/any/fonts/missing.woff2").header(origin()).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_index_dependencies() {
+        use std::fs;
+        use std::io::Write;
+
+        let dependency = std::env::temp_dir().join("rocket-static-index-dependency.js");
+        fs::write(&dependency, "let version = 1;").unwrap();
+
+        let files = StaticFiles::from(static_root())
+            .index_dependencies(vec![&dependency, &static_root().join("missing.js")]);
+
+        let rocket = rocket::ignite().mount("/", files);
+        let client = Client::new(rocket).expect("valid rocket");
+        let validators = |path: &str| {
+            let response = client.get(path).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let header = |name| response.headers().get_one(name).map(|s| s.to_string());
+            (header("ETag"), header("Last-Modified"))
+        };
+
+        let (index_etag, _) = validators("/");
+        assert!(index_etag.is_some());
+        assert_eq!(validators("/index.html").0, index_etag);
+
+        let (plain_etag, _) = validators("/other/hello.txt");
+        let expected = fs::metadata(static_root().join("other/hello.txt")).unwrap();
+        let expected = rocket::response::Validators::from_metadata(&expected).unwrap();
+        assert_eq!(plain_etag, Some(expected.etag().to_string()));
+
+        let mut file = fs::OpenOptions::new().append(true).open(&dependency).unwrap();
+        file.write_all(b" version = 2;").unwrap();
+        assert_ne!(validators("/").0, index_etag);
+
+        fs::remove_file(&dependency).unwrap();
+    }
+
+    #[test]
+    fn test_index_disabled() {
+        let root = static_root();
+        let rocket = rocket::ignite()
+            .mount("/assets", StaticFiles::new(&root, Options::DotFiles | Options::Index).index_disabled());
+
+        let client = Client::new(rocket).expect("valid rocket");
+        assert_eq!(client.get("/assets/inner/").dispatch().status(), Status::NotFound);
+        assert_eq!(client.get("/assets/inner").dispatch().status(), Status::NotFound);
+        assert_eq!(client.get("/assets/").dispatch().status(), Status::NotFound);
+        assert_file(&client, "assets", "inner/index.html", true);
+        assert_file(&client, "assets", "inner/.hideme", true);
+    }
+
+    #[test]
+    fn test_cache_index() {
+        use std::cell::Cell;
+        use std::fs;
+        use std::io::Write;
+        use std::time::{Duration, Instant};
+        use rocket::http::Header;
+        use rocket::response::MetadataCache;
+
+        thread_local! {
+            static START: Instant = Instant::now();
+            static ELAPSED: Cell<Duration> = Cell::new(Duration::from_secs(0));
+        }
+
+        fn clock() -> Instant {
+            START.with(|start| *start + ELAPSED.with(|elapsed| elapsed.get()))
+        }
+
+        let root = std::env::temp_dir().join("rocket-static-cache-index");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("data.txt"), "first").unwrap();
+
+        let index = MetadataCache::new(16).ttl(Duration::from_secs(30)).clock(clock);
+        let files = StaticFiles::from(&root).index(index);
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+        let response = client.get("/data.txt").dispatch();
+        let etag = response.headers().get_one("ETag").expect("etag").to_string();
+
+        // Until the entry expires, the indexed metadata answers conditional requests.
+        let mut file = fs::OpenOptions::new().append(true).open(root.join("data.txt")).unwrap();
+        file.write_all(b", second").unwrap();
+
+        let response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        // Opening the file reveals its new length, which refreshes the entry.
+        let mut response = client.get("/data.txt").dispatch();
+        let new_etag = response.headers().get_one("ETag").expect("etag").to_string();
+        assert_ne!(new_etag, etag);
+        assert_eq!(response.body_string(), Some("first, second".into()));
+
+        let mut response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("ETag"), Some(&*new_etag));
+        assert_eq!(response.body_string(), Some("first, second".into()));
+
+        // Once it expires, the index no longer answers for the file.
+        fs::remove_file(root.join("data.txt")).unwrap();
+        let response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", new_etag.clone()))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        ELAPSED.with(|elapsed| elapsed.set(Duration::from_secs(30)));
+        let response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", new_etag))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotFound);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_cache_index_respects_overlays() {
+        use std::fs;
+        use std::time::Duration;
+        use rocket::http::Header;
+
+        let root = std::env::temp_dir()
+            .join(format!("rocket-static-cache-index-overlay-{}", std::process::id()));
+
+        fs::create_dir_all(root.join("top")).unwrap();
+        fs::create_dir_all(root.join("base")).unwrap();
+        fs::write(root.join("base/data.txt"), "base").unwrap();
+
+        let files = StaticFiles::overlay(vec![root.join("top"), root.join("base")])
+            .cache_index(Duration::from_secs(60));
+
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+        let response = client.get("/data.txt").dispatch();
+        let etag = response.headers().get_one("ETag").expect("etag").to_string();
+
+        let response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        // Once the top layer has the file, the base layer's entry no longer applies.
+        fs::write(root.join("top/data.txt"), "top, overriding").unwrap();
+        let mut response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_ne!(response.headers().get_one("ETag"), Some(&*etag));
+        assert_eq!(response.body_string(), Some("top, overriding".into()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_cache_index_answers_without_file_system() {
+        use std::fs;
+        use std::time::Duration;
+        use rocket::http::Header;
+
+        let root = std::env::temp_dir().join("rocket-static-cache-index-unread");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("data.txt"), "data").unwrap();
+
+        let files = StaticFiles::from(&root).cache_index(Duration::from_secs(60));
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+        let response = client.get("/data.txt").dispatch();
+        let etag = response.headers().get_one("ETag").expect("etag").to_string();
+        let last_modified = response.headers().get_one("Last-Modified").unwrap().to_string();
+
+        // With the file gone, only the index can answer conditional requests.
+        fs::remove_file(root.join("data.txt")).unwrap();
+        let mut response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+        assert_eq!(response.headers().get_one("ETag"), Some(&*etag));
+        assert_eq!(response.headers().get_one("Last-Modified"), Some(&*last_modified));
+        assert!(response.body_bytes().is_none());
+
+        let response = client.get("/data.txt")
+            .header(Header::new("If-Modified-Since", last_modified))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        // Other requests need the file.
+        let response = client.get("/data.txt")
+            .header(Header::new("If-None-Match", "\"some-other-tag\""))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(client.get("/data.txt").dispatch().status(), Status::NotFound);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_immutable_if_fingerprinted() {
+        use rocket_contrib::serve::CacheControl;
+
+        let root = static_root();
+        let rocket = rocket::ignite()
+            .mount("/auto", StaticFiles::from(&root).immutable_if_fingerprinted())
+            .mount("/ruled", StaticFiles::from(&root)
+                .immutable_if_fingerprinted()
+                .cache_rule("other/*", CacheControl::max_age(60))
+                .cache_control(CacheControl::max_age(5)));
+
+        let client = Client::new(rocket).expect("valid rocket");
+        let cache_control = |path: &str| {
+            let response = client.get(path).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            response.headers().get_one("Cache-Control").map(|s| s.to_string())
+        };
+
+        let immutable = Some("public, max-age=31536000, immutable".to_string());
+        assert_eq!(cache_control("/auto/builds/v3/app.3f9a2b.js"), immutable);
+        assert_eq!(cache_control("/auto/builds/v2/app.js"), Some("no-cache".into()));
+        assert_eq!(cache_control("/auto/index.html"), Some("no-cache".into()));
+
+        assert_eq!(cache_control("/ruled/builds/v3/app.3f9a2b.js"), immutable);
+        assert_eq!(cache_control("/ruled/other/hello.txt"), Some("max-age=60".into()));
+        assert_eq!(cache_control("/ruled/index.html"), Some("max-age=5".into()));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_json_listing() {
+        use rocket::http::{Accept, ContentType};
+
+        let root = static_root();
+        let rocket = rocket::ignite()
+            .mount("/listing", StaticFiles::new(&root, Options::Index | Options::JsonListing));
+
+        let client = Client::new(rocket).expect("valid rocket");
+        let mut response = client.get("/listing/inner/").header(Accept::JSON).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        assert_eq!(response.headers().get_one("Vary"), Some("Accept"));
+
+        let body = response.body_string().expect("listing body");
+        let listing: serde_json::Value = serde_json::from_str(&body).expect("valid JSON");
+        let entries = listing.as_array().expect("array listing");
+        let names: Vec<_> = entries.iter().map(|entry| entry["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["goodbye", "index.html"]);
+
+        for entry in entries {
+            let path = root.join("inner").join(entry["name"].as_str().unwrap());
+            let metadata = std::fs::metadata(path).unwrap();
+            assert_eq!(entry["size"].as_u64(), Some(metadata.len()));
+            assert_eq!(entry["is_dir"].as_bool(), Some(false));
+            assert!(entry["modified"].is_u64());
+        }
+
+        let mut response = client.get("/listing/").header(Accept::JSON).dispatch();
+        let listing: serde_json::Value = serde_json::from_str(&response.body_string().unwrap())
+            .expect("valid JSON");
+
+        let inner = listing.as_array().unwrap().iter()
+            .find(|entry| entry["name"] == "inner")
+            .expect("inner directory listed");
+
+        assert_eq!(inner["is_dir"].as_bool(), Some(true));
+
+        // Other requests for directories still receive the index file.
+        assert_file(&client, "listing", "inner/", true);
+        let response = client.get("/listing/inner/").header(Accept::HTML).dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+    }
+
+    #[test]
+    fn test_default_cache_control() {
+        use rocket::{Request, Data};
+        use rocket::handler::Outcome;
+        use rocket::http::Method;
+        use rocket_contrib::serve::{CacheControl, DefaultCacheControl};
+
+        fn text<'r>(req: &'r Request<'_>, _: Data) -> Outcome<'r> {
+            Outcome::from(req, "not a file")
+        }
+
+        fn missing<'r>(req: &'r Request<'_>, _: Data) -> Outcome<'r> {
+            use rocket::response::{status, NamedFile};
+
+            let page = NamedFile::open(static_root().join("other/hello.txt")).ok();
+            Outcome::from(req, status::NotFound(page))
+        }
+
+        let root = static_root();
+        let routes = vec![
+            Route::new(Method::Get, "/text", text),
+            Route::new(Method::Get, "/missing", missing),
+        ];
+
+        let rocket = rocket::ignite()
+            .attach(DefaultCacheControl::new(CacheControl::max_age(3600)))
+            .mount("/plain", StaticFiles::from(&root))
+            .mount("/own", StaticFiles::from(&root).cache_control(CacheControl::no_cache()))
+            .mount("/", routes);
+
+        let client = Client::new(rocket).expect("valid rocket");
+        let response = client.get("/plain/other/hello.txt").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Cache-Control"), Some("max-age=3600"));
+
+        let response = client.get("/own/other/hello.txt").dispatch();
+        assert_eq!(response.headers().get_one("Cache-Control"), Some("no-cache"));
+
+        let response = client.get("/text").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().get_one("Cache-Control").is_none());
+
+        // A file sent as an error page isn't cached as the requested resource.
+        let response = client.get("/missing").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(response.headers().get_one("Cache-Control").is_none());
+    }
+
+    fn compression_root(test: &str) -> PathBuf {
+        use std::fs;
+
+        let root = std::env::temp_dir().join("rocket-static-compression").join(test);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("app.js"), "console.log('hi');").unwrap();
+        fs::write(root.join("app.js.gz"), "gzip bytes").unwrap();
+        fs::write(root.join("app.js.br"), "brotli bytes").unwrap();
+        fs::write(root.join("small.txt"), "tiny").unwrap();
+        fs::write(root.join("large.txt"), "Hello, compression! ".repeat(256)).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_compression_precompressed_sibling() {
+        use rocket::http::{ContentType, Header};
+        use rocket_contrib::serve::CompressionMode;
+
+        let root = compression_root("precompressed_sibling");
+        let rocket = rocket::ignite()
+            .mount("/pre", StaticFiles::from(&root).compression(CompressionMode::Precompressed))
+            .mount("/off", StaticFiles::from(&root));
+
+        let client = Client::new(rocket).expect("valid rocket");
+        let get = |path: &str, encodings: &str| {
+            let accept_encoding = Header::new("Accept-Encoding", encodings.to_string());
+            client.get(path.to_string()).header(accept_encoding).dispatch()
+        };
+
+        let mut response = get("/pre/app.js", "gzip");
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JavaScript));
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+        assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+        assert_eq!(response.body_string(), Some("gzip bytes".into()));
+
+        let mut response = get("/pre/app.js", "gzip, br");
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("br"));
+        assert_eq!(response.body_string(), Some("brotli bytes".into()));
+
+        let mut response = get("/pre/app.js", "br;q=0, identity");
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+        assert_eq!(response.body_string(), Some("console.log('hi');".into()));
+
+        // Any weight of zero refuses an encoding.
+        for refusal in &["br;q=0.0, gzip;q=0.000", "br; q=0.00, gzip;Q=0"] {
+            let response = get("/pre/app.js", refusal);
+            assert!(response.headers().get_one("Content-Encoding").is_none(), "{}", refusal);
+        }
+
+        let response = get("/pre/app.js", "br;q=0.0, gzip;q=0.001");
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+
+        let mut response = get("/off/app.js", "gzip, br");
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+        assert!(response.headers().get_one("Vary").is_none());
+        assert_eq!(response.body_string(), Some("console.log('hi');".into()));
+    }
+
+    #[test]
+    #[cfg(feature = "gzip_compression")]
+    fn test_compression_on_the_fly() {
+        use std::io::Read;
+        use flate2::read::GzDecoder;
+        use rocket::http::Header;
+        use rocket_contrib::serve::CompressionMode;
+
+        let root = compression_root("on_the_fly");
+        let files = StaticFiles::from(&root).compression(CompressionMode::Auto);
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+
+        let mut response = client.get("/large.txt")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+
+        let mut body = String::new();
+        let compressed = response.body_bytes().unwrap();
+        GzDecoder::new(&compressed[..]).read_to_string(&mut body).unwrap();
+        assert_eq!(body, "Hello, compression! ".repeat(256));
+
+        // The precompressed sibling is still preferred.
+        let mut response = client.get("/app.js")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+
+        assert_eq!(response.body_string(), Some("gzip bytes".into()));
+    }
+
+    #[test]
+    fn test_compression_pass_through() {
+        use rocket::http::Header;
+        use rocket_contrib::serve::CompressionMode;
+
+        let root = compression_root("pass_through");
+        let files = StaticFiles::from(&root).compression(CompressionMode::Auto);
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+
+        // Files too small to be worth compressing are sent as they are.
+        let mut response = client.get("/small.txt")
+            .header(Header::new("Accept-Encoding", "gzip, br"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+        assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+        assert_eq!(response.body_string(), Some("tiny".into()));
+
+        // As are files requested by clients that don't accept compression.
+        let mut response = client.get("/large.txt").dispatch();
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+        assert_eq!(response.body_string(), Some("Hello, compression! ".repeat(256)));
+    }
+
+    #[test]
+    fn test_conditional_index_requests() {
+        use rocket::http::Header;
+
+        let client = Client::new(rocket()).expect("valid rocket");
+        for path in &["/default/", "/default/inner/"] {
+            let response = client.get(*path).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let last_modified = response.headers().get_one("Last-Modified")
+                .expect("last modified")
+                .to_string();
+
+            let etag = response.headers().get_one("ETag").expect("etag").to_string();
+
+            let mut response = client.get(*path)
+                .header(Header::new("If-Modified-Since", last_modified))
+                .dispatch();
+
+            assert_eq!(response.status(), Status::NotModified);
+            assert!(response.body_bytes().is_none());
+
+            let response = client.get(*path).header(Header::new("If-None-Match", etag)).dispatch();
+            assert_eq!(response.status(), Status::NotModified);
+        }
+    }
+
+    #[test]
+    fn test_exact_length() {
+        use rocket::http::Header;
+        use rocket::response::Body;
+        use rocket_contrib::serve::CompressionMode;
+
+        let root = compression_root("exact_length");
+        let len = std::fs::metadata(root.join("large.txt")).unwrap().len();
+        let files = StaticFiles::from(&root).compression(CompressionMode::Auto).exact_length();
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+
+        let mut response = client.get("/large.txt")
+            .header(Header::new("Accept-Encoding", "gzip, br"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().get_one("Content-Encoding").is_none());
+        assert_eq!(response.headers().get_one("X-Content-Length"), Some(&*len.to_string()));
+        match response.body() {
+            Some(Body::Sized(_, size)) => assert_eq!(size, len),
+            _ => panic!("expected a sized body")
+        }
+
+        let response = client.get("/large.txt").header(Header::new("Range", "bytes=0-9")).dispatch();
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.headers().get_one("X-Content-Length"), Some(&*len.to_string()));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let root = static_root();
+        let rocket = rocket::ignite()
+            .mount("/ci", StaticFiles::from(&root).case_insensitive())
+            .mount("/cs", StaticFiles::from(&root));
+
+        let client = Client::new(rocket).expect("valid rocket");
+        let hello = std::fs::read_to_string(root.join("other/hello.txt")).unwrap();
+        for path in &["/ci/OTHER/Hello.TXT", "/ci/other/hello.txt", "/ci/Other/HELLO.txt"] {
+            let mut response = client.get(*path).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.body_string(), Some(hello.clone()));
+        }
+
+        let index = std::fs::read_to_string(root.join("inner/index.html")).unwrap();
+        let mut response = client.get("/ci/INNER/").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some(index));
+
+        assert_eq!(client.get("/ci/OTHER/missing.txt").dispatch().status(), Status::NotFound);
+        assert_eq!(client.get("/cs/OTHER/Hello.TXT").dispatch().status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_case_insensitive_bounds_directory_reads() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use rocket_contrib::serve::{FileProvider, VirtualFile};
+
+        /// A directory of a million files that counts the names it lists.
+        struct Huge(Arc<AtomicUsize>);
+
+        impl FileProvider for Huge {
+            fn open(&self, path: &Path) -> Option<VirtualFile> {
+                match path.to_str() {
+                    Some("file7.txt") => Some(VirtualFile::from_memory(path, &b"7"[..], None)),
+                    _ => None
+                }
+            }
+
+            fn list_names(&self, _: &Path, limit: usize) -> Option<Vec<String>> {
+                let listed = self.0.clone();
+                let names = (0..1_000_000)
+                    .map(|i| format!("file{}.txt", i))
+                    .take(limit)
+                    .inspect(|_| { listed.fetch_add(1, Ordering::SeqCst); })
+                    .collect();
+
+                Some(names)
+            }
+        }
+
+        let listed = Arc::new(AtomicUsize::new(0));
+        let files = StaticFiles::from_provider(Huge(listed.clone())).case_insensitive();
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+
+        assert_eq!(client.get("/file7.txt").dispatch().status(), Status::Ok);
+        assert_eq!(listed.load(Ordering::SeqCst), 0);
+
+        assert_eq!(client.get("/FILE7.txt").dispatch().status(), Status::NotFound);
+        assert_eq!(listed.load(Ordering::SeqCst), 1025);
+    }
+
+    #[test]
+    fn test_case_insensitive_skips_existing_paths() {
+        use std::fs;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use rocket_contrib::serve::{FileProvider, VirtualFile};
+
+        /// A directory on the file system that counts how often it is listed.
+        struct Counted(PathBuf, Arc<AtomicUsize>);
+
+        impl FileProvider for Counted {
+            fn open(&self, path: &Path) -> Option<VirtualFile> {
+                self.0.open(path)
+            }
+
+            fn list_names(&self, path: &Path, limit: usize) -> Option<Vec<String>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.list_names(path, limit)
+            }
+
+            fn locate(&self, path: &Path) -> Option<PathBuf> {
+                self.0.locate(path)
+            }
+        }
+
+        let root = std::env::temp_dir()
+            .join(format!("rocket-static-case-insensitive-dir-{}", std::process::id()));
+
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/notes.txt"), "notes").unwrap();
+
+        let listed = Arc::new(AtomicUsize::new(0));
+        let files = StaticFiles::from_provider(Counted(root.clone(), listed.clone()))
+            .options(Options::None)
+            .case_insensitive();
+
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+
+        // A directory exists as is, so it isn't matched case-insensitively.
+        assert_eq!(client.get("/docs/").dispatch().status(), Status::NotFound);
+        assert_eq!(listed.load(Ordering::SeqCst), 0);
+
+        let mut response = client.get("/DOCS/Notes.txt").dispatch();
+        assert_eq!(response.body_string(), Some("notes".into()));
+        assert!(listed.load(Ordering::SeqCst) > 0);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_image_negotiation() {
+        use std::fs;
+        use rocket::http::{Accept, ContentType, Header};
+
+        let root = std::env::temp_dir().join("rocket-static-image-negotiation");
+        fs::create_dir_all(&root).unwrap();
+        for (name, contents) in &[
+            ("hero.jpg", "jpeg hero"), ("hero.avif", "avif hero"), ("hero.webp", "webp hero"),
+            ("photo.jpg", "jpeg photo"), ("photo.webp", "webp photo"),
+            ("plain.jpg", "jpeg plain"),
+        ] {
+            fs::write(root.join(name), contents).unwrap();
+        }
+
+        let files = StaticFiles::from(&root).image_negotiation();
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+        let modern = Header::new("Accept", "image/avif,image/webp,image/*,*/*;q=0.8");
+
+        // AVIF is preferred when it is accepted and available.
+        let mut response = client.get("/hero.jpg").header(modern.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Content-Type"), Some("image/avif"));
+        assert_eq!(response.headers().get_one("Vary"), Some("Accept"));
+        assert_eq!(response.body_string(), Some("avif hero".into()));
+
+        let webp_only = Header::new("Accept", "image/avif;q=0, image/webp");
+        let mut response = client.get("/hero.jpg").header(webp_only).dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::WEBP));
+        assert_eq!(response.body_string(), Some("webp hero".into()));
+
+        // WebP is used when there is no AVIF image.
+        let mut response = client.get("/photo.jpg").header(modern.clone()).dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::WEBP));
+        assert_eq!(response.body_string(), Some("webp photo".into()));
+
+        // The original is served when there is no other format or when no
+        // other format is accepted.
+        let mut response = client.get("/plain.jpg").header(modern).dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::JPEG));
+        assert_eq!(response.body_string(), Some("jpeg plain".into()));
+
+        let mut response = client.get("/hero.jpg").header(Accept::Any).dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::JPEG));
+        assert_eq!(response.body_string(), Some("jpeg hero".into()));
+    }
+
+    #[test]
+    fn test_ranges_for_media_types() {
+        use std::fs;
+        use rocket::http::{ContentType, Header};
+
+        let root = std::env::temp_dir().join("rocket-static-ranges-for");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("video.mp4"), "mp4 video").unwrap();
+        fs::write(root.join("notes.txt"), "some notes").unwrap();
+
+        let files = StaticFiles::from(&root).ranges_for(&[ContentType::MP4]);
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+
+        let mut response = client.get("/video.mp4").header(Header::new("Range", "bytes=0-2"))
+            .dispatch();
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("bytes"));
+        assert_eq!(response.headers().get_one("Content-Range"), Some("bytes 0-2/9"));
+        assert_eq!(response.body_string(), Some("mp4".into()));
+
+        let mut response = client.get("/notes.txt").header(Header::new("Range", "bytes=0-2"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("none"));
+        assert!(response.headers().get_one("Content-Range").is_none());
+        assert_eq!(response.body_string(), Some("some notes".into()));
+    }
+
+    #[test]
+    fn test_autoindex_template() {
+        use std::fs;
+        use rocket::http::ContentType;
+
+        let root = std::env::temp_dir().join("rocket-static-autoindex");
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("a <b>.txt"), "a").unwrap();
+        fs::write(root.join(".hidden"), "hidden").unwrap();
+
+        let files = StaticFiles::new(&root, Options::AutoIndex);
+        let custom = StaticFiles::from(&root).autoindex_template(|entries| {
+            let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+            format!("<ol class=\"custom\">{}</ol>", names.join("|"))
+        });
+
+        let rocket = rocket::ignite().mount("/plain", files).mount("/custom", custom);
+        let client = Client::new(rocket).expect("valid rocket");
+
+        let mut response = client.get("/plain/").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        let body = response.body_string().unwrap();
+        assert!(body.contains("<a href=\"./a%20%3Cb%3E.txt\">a &lt;b&gt;.txt</a>"));
+        assert!(body.contains("<a href=\"./docs/\">docs/</a>"));
+        assert!(!body.contains("hidden"));
+
+        let mut response = client.get("/custom/").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        assert_eq!(response.body_string(), Some("<ol class=\"custom\">a <b>.txt|docs</ol>".into()));
+
+        // Files are still served, and missing directories aren't listed.
+        let mut response = client.get("/custom/a%20%3Cb%3E.txt").dispatch();
+        assert_eq!(response.body_string(), Some("a".into()));
+        assert_eq!(client.get("/custom/missing/").dispatch().status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_autoindex_merges_overlays() {
+        use std::fs;
+
+        let root = std::env::temp_dir()
+            .join(format!("rocket-static-autoindex-overlay-{}", std::process::id()));
+
+        fs::create_dir_all(root.join("top/docs")).unwrap();
+        fs::create_dir_all(root.join("base/docs")).unwrap();
+        fs::write(root.join("top/docs/shared.txt"), "top").unwrap();
+        fs::write(root.join("top/docs/top.txt"), "top").unwrap();
+        fs::write(root.join("base/docs/shared.txt"), "base, longer").unwrap();
+        fs::write(root.join("base/docs/base.txt"), "base").unwrap();
+
+        let files = StaticFiles::overlay(vec![root.join("top"), root.join("base")])
+            .options(Options::AutoIndex)
+            .autoindex_template(|entries| {
+                let entries: Vec<_> = entries.iter()
+                    .map(|entry| format!("{}:{}", entry.name, entry.size))
+                    .collect();
+
+                entries.join("|")
+            });
+
+        let client = Client::new(rocket::ignite().mount("/", files)).expect("valid rocket");
+        let mut response = client.get("/docs/").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("base.txt:4|shared.txt:3|top.txt:3".into()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,54 @@
+#![feature(proc_macro_hygiene)]
+
+#[macro_use] extern crate rocket;
+
+use rocket::State;
+use rocket::config::{Environment, Config, LoggingLevel};
+use rocket::response::{NamedFile, MetadataCache};
+
+const FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+
+#[get("/uncached")]
+fn uncached() -> Option<NamedFile> {
+    NamedFile::open(FILE).ok()
+}
+
+#[get("/cached")]
+fn cached(cache: State<'_, MetadataCache>) -> Option<NamedFile> {
+    NamedFile::open_cached(FILE, &cache).ok()
+}
+
+fn rocket() -> rocket::Rocket {
+    let config = Config::build(Environment::Production).log_level(LoggingLevel::Off);
+    rocket::custom(config.unwrap())
+        .manage(MetadataCache::new(16))
+        .mount("/", routes![uncached, cached])
+}
+
+mod benches {
+    extern crate test;
+
+    use super::rocket;
+    use self::test::Bencher;
+    use rocket::local::Client;
+
+    #[bench]
+    fn bench_uncached_named_file(b: &mut Bencher) {
+        let client = Client::new(rocket()).unwrap();
+        let mut request = client.get("/uncached");
+
+        b.iter(|| {
+            request.mut_dispatch().body_bytes();
+        });
+    }
+
+    #[bench]
+    fn bench_cached_named_file(b: &mut Bencher) {
+        let client = Client::new(rocket()).unwrap();
+        let mut request = client.get("/cached");
+
+        b.iter(|| {
+            request.mut_dispatch().body_bytes();
+        });
+    }
+}
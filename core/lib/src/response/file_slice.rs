@@ -0,0 +1,120 @@
+use std::cmp;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::request::Request;
+use crate::response::{self, Responder, DEFAULT_CHUNK_SIZE};
+use crate::response::named_file::{respond_with, ResponseOptions};
+use crate::http::{ContentType, Status};
+
+/// A region of an open file, served as if it were a file of its own.
+///
+/// A `FileSlice` is created with [`NamedFile::from_file_range()`] from a
+/// `File`, the offset at which the region starts, the region's length, and the
+/// region's `Content-Type`. It responds with exactly the bytes in the region,
+/// with a `Content-Length` of the region's length, and honors byte range
+/// requests relative to the region.
+///
+/// [`NamedFile::from_file_range()`]: crate::response::NamedFile::from_file_range()
+#[derive(Debug)]
+pub struct FileSlice {
+    file: File,
+    offset: u64,
+    len: u64,
+    content_type: ContentType,
+}
+
+impl FileSlice {
+    pub(crate) fn new(file: File, offset: u64, len: u64, content_type: ContentType) -> FileSlice {
+        FileSlice { file, offset, len, content_type }
+    }
+
+    /// Returns the offset of the region in the file.
+    #[inline(always)]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the length of the region in bytes.
+    #[inline(always)]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the region is empty.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Streams the region of the file to the client with the `Content-Type` given
+/// at construction. Byte range requests are evaluated as described for
+/// [`NamedFile`](crate::response::NamedFile), with offsets relative to the
+/// start of the region. Fails with a **500 Internal Server Error** if the file
+/// can't be positioned at the start of the region.
+impl Responder<'_> for FileSlice {
+    fn respond_to(self, req: &Request<'_>) -> response::Result<'static> {
+        let mut file = BufReader::new(self.file);
+        file.seek(SeekFrom::Start(self.offset)).map_err(|e| {
+            error_!("Failed to seek to start of file slice: {:?}", e);
+            Status::InternalServerError
+        })?;
+
+        let window = Window { inner: file, offset: self.offset, len: self.len, pos: 0 };
+        let (path, size) = (Path::new(""), Some(self.len));
+        let (chunk_size, options) = (DEFAULT_CHUNK_SIZE, ResponseOptions::default());
+        let mut response = respond_with(req, path, window, size, chunk_size, None, options)?;
+        response.set_header(self.content_type);
+        Ok(response)
+    }
+}
+
+/// A reader of the `len` bytes of `inner` starting at `offset`, where `inner`
+/// is positioned at `offset + pos`.
+struct Window<R> {
+    inner: R,
+    offset: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<R: Read> Read for Window<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = cmp::min(buf.len() as u64, self.len.saturating_sub(self.pos)) as usize;
+        let n = self.inner.read(&mut buf[..max])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Window<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => offset_by(self.len, n),
+            SeekFrom::Current(n) => offset_by(self.pos, n),
+        };
+
+        let pos = pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a negative position")
+        })?;
+
+        let inner_pos = self.offset.checked_add(pos).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a position that overflows")
+        })?;
+
+        self.inner.seek(SeekFrom::Start(inner_pos))?;
+        self.pos = pos;
+        Ok(pos)
+    }
+}
+
+/// Returns `base + delta` or `None` if it is negative.
+fn offset_by(base: u64, delta: i64) -> Option<u64> {
+    match delta >= 0 {
+        true => base.checked_add(delta as u64),
+        false => base.checked_sub(delta.wrapping_neg() as u64),
+    }
+}
@@ -0,0 +1,143 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::request::Request;
+use crate::response::{self, Responder, Validators, DEFAULT_CHUNK_SIZE};
+use crate::response::named_file::{respond_with, ResponseOptions};
+
+/// A file held in memory that responds like a [`NamedFile`].
+///
+/// A `MemoryFile` has a path, which determines the `Content-Type` of the
+/// response, and contents. If it has a modification time, set via
+/// [`MemoryFile::modified()`], it responds to conditional requests as a
+/// `NamedFile` does. Byte range requests are honored unless disabled via
+/// [`MemoryFile::accept_ranges()`].
+///
+/// [`NamedFile`]: crate::response::NamedFile
+///
+/// # Example
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// # #[macro_use] extern crate rocket;
+/// use rocket::response::MemoryFile;
+///
+/// #[get("/app.css")]
+/// fn stylesheet() -> MemoryFile {
+///     MemoryFile::new("app.css", &b"body { margin: 0; }"[..])
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryFile {
+    path: PathBuf,
+    data: Cow<'static, [u8]>,
+    validators: Option<Validators>,
+    ranges: bool,
+}
+
+impl MemoryFile {
+    /// Returns a file named `path` with contents `data` and no modification
+    /// time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::MemoryFile;
+    ///
+    /// let file = MemoryFile::new("hello.txt", "Hello, world!".as_bytes());
+    /// assert_eq!(file.data(), b"Hello, world!");
+    /// ```
+    pub fn new<P, D>(path: P, data: D) -> MemoryFile
+        where P: AsRef<Path>, D: Into<Cow<'static, [u8]>>
+    {
+        let (path, data) = (path.as_ref().to_path_buf(), data.into());
+        MemoryFile { path, data, validators: None, ranges: true }
+    }
+
+    /// Sets the modification time of `self` to `modified`, allowing it to
+    /// respond to conditional requests.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::SystemTime;
+    /// use rocket::response::MemoryFile;
+    ///
+    /// let file = MemoryFile::new("hello.txt", vec![b'h', b'i'])
+    ///     .modified(SystemTime::now());
+    /// ```
+    pub fn modified(mut self, modified: SystemTime) -> MemoryFile {
+        self.validators = Some(Validators::new(self.data.len() as u64, modified));
+        self
+    }
+
+    /// Replaces the validators of `self`, used to respond to conditional
+    /// requests, with `validators`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::SystemTime;
+    /// use rocket::response::{MemoryFile, Validators};
+    ///
+    /// let validators = Validators::new(0, SystemTime::now());
+    /// let file = MemoryFile::new("hello.txt", vec![b'h', b'i'])
+    ///     .with_validators(validators.clone());
+    ///
+    /// assert_eq!(file.validators(), Some(&validators));
+    /// ```
+    pub fn with_validators(mut self, validators: Validators) -> MemoryFile {
+        self.validators = Some(validators);
+        self
+    }
+
+    /// Sets whether byte range requests are honored to `enabled`. By default,
+    /// they are. When disabled, the response includes an `Accept-Ranges:
+    /// none` header and `Range` headers are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::MemoryFile;
+    ///
+    /// let file = MemoryFile::new("hello.txt", vec![b'h', b'i']).accept_ranges(false);
+    /// ```
+    pub fn accept_ranges(mut self, enabled: bool) -> MemoryFile {
+        self.ranges = enabled;
+        self
+    }
+
+    /// Returns the validators of `self`, if it has any.
+    #[inline(always)]
+    pub fn validators(&self) -> Option<&Validators> {
+        self.validators.as_ref()
+    }
+
+    /// Returns the path of `self`.
+    #[inline(always)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the contents of `self`.
+    #[inline(always)]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Responds with the contents of the file. Sets or overrides the Content-Type
+/// in the response according to the file's extension and evaluates conditional
+/// and byte range requests, unless disabled, as described for
+/// [`NamedFile`](crate::response::NamedFile).
+impl Responder<'_> for MemoryFile {
+    fn respond_to(self, req: &Request<'_>) -> response::Result<'static> {
+        let size = self.data.len() as u64;
+        let body = Cursor::new(self.data);
+        let options = ResponseOptions { ranges: self.ranges, ..ResponseOptions::default() };
+        let validators = self.validators;
+        respond_with(req, &self.path, body, Some(size), DEFAULT_CHUNK_SIZE, validators, options)
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::request::Request;
+use crate::response::{self, Responder};
+
+/// Reports the number of body bytes sent by the wrapped responder.
+///
+/// A `Metered` responder wraps another responder, such as a [`NamedFile`],
+/// and a callback. The callback is called exactly once, after the response has
+/// been sent or abandoned, with the number of bytes of the body that were read
+/// to be sent. This accounts for partial responses to byte range requests and
+/// for `HEAD` requests, whose body is never sent, as well as for responses
+/// whose body is streamed lazily. If the response has no body, the callback is
+/// called with `0` while responding.
+///
+/// The callback is not called if the wrapped responder fails.
+///
+/// [`NamedFile`]: crate::response::NamedFile
+///
+/// # Example
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// # #[macro_use] extern crate rocket;
+/// use std::path::PathBuf;
+/// use rocket::response::{Metered, NamedFile};
+///
+/// #[get("/downloads/<path..>")]
+/// fn download(path: PathBuf) -> Option<Metered<NamedFile>> {
+///     let file = NamedFile::open(PathBuf::from("downloads").join(&path)).ok()?;
+///     Some(Metered::new(file, move |sent| println!("sent {} bytes of {:?}", sent, path)))
+/// }
+/// ```
+pub struct Metered<R> {
+    responder: R,
+    report: Box<dyn FnOnce(u64) + Send>,
+}
+
+impl<R> Metered<R> {
+    /// Wraps `responder` so that the number of body bytes sent is reported to
+    /// `report` once the response is complete.
+    pub fn new<F>(responder: R, report: F) -> Metered<R>
+        where F: FnOnce(u64) + Send + 'static
+    {
+        Metered { responder, report: Box::new(report) }
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for Metered<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metered").field("responder", &self.responder).finish()
+    }
+}
+
+/// Responds with the response of the wrapped responder, counting the bytes
+/// read from its body.
+impl<'r, R: Responder<'r>> Responder<'r> for Metered<R> {
+    fn respond_to(self, req: &Request<'_>) -> response::Result<'r> {
+        let mut response = self.responder.respond_to(req)?;
+        let meter = Meter { sent: 0, report: Some(self.report) };
+        if let Some(body) = response.take_body() {
+            response.set_raw_body(body.map(|inner| Counted { inner, meter }));
+        }
+
+        Ok(response)
+    }
+}
+
+/// The number of bytes sent, reported when dropped.
+struct Meter {
+    sent: u64,
+    report: Option<Box<dyn FnOnce(u64) + Send>>,
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        if let Some(report) = self.report.take() {
+            report(self.sent);
+        }
+    }
+}
+
+/// A reader that counts the bytes read from `inner` in `meter`.
+struct Counted<R> {
+    inner: R,
+    meter: Meter,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.meter.sent += n as u64;
+        Ok(n)
+    }
+}
@@ -23,6 +23,11 @@
 mod responder;
 mod redirect;
 mod named_file;
+mod memory_file;
+mod file_slice;
+mod negotiated_file;
+mod validators;
+mod metered;
 mod stream;
 mod response;
 mod debug;
@@ -38,7 +43,12 @@ pub use self::response::{Response, ResponseBuilder, Body, DEFAULT_CHUNK_SIZE};
 pub use self::responder::Responder;
 pub use self::redirect::Redirect;
 pub use self::flash::Flash;
-pub use self::named_file::NamedFile;
+pub use self::named_file::{NamedFile, MetadataCache, Disposition};
+pub use self::memory_file::MemoryFile;
+pub use self::file_slice::FileSlice;
+pub use self::negotiated_file::NegotiatedFile;
+pub use self::validators::{Validators, CacheDecision};
+pub use self::metered::Metered;
 pub use self::stream::Stream;
 pub use self::debug::Debug;
 #[doc(inline)] pub use self::content::Content;
@@ -1,18 +1,89 @@
-use std::fs::File;
+use std::fs::{File, Metadata};
 use std::path::{Path, PathBuf};
-use std::io;
+use std::io::{self, BufReader, Read, Seek};
 use std::ops::{Deref, DerefMut};
+use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::{mpsc, Mutex};
+use std::thread;
 
 use crate::request::Request;
-use crate::response::{self, Responder};
-use crate::http::ContentType;
+use crate::response::{self, Responder, Response, Body, Validators, MemoryFile, CacheDecision};
+use crate::response::FileSlice;
+use crate::response::responder::{known_size, is_empty_file};
+use crate::http::{ContentType, Status};
+use crate::http::hyper::header::{ContentRange, ContentRangeSpec::Bytes};
 
 /// A file with an associated name; responds with the Content-Type based on the
 /// file extension.
 #[derive(Debug)]
-pub struct NamedFile(PathBuf, File);
+pub struct NamedFile {
+    path: PathBuf,
+    file: File,
+    info: Option<FileInfo>,
+    download_name: Option<String>,
+    disposition: Option<Disposition>,
+    buffer_size: usize,
+    status: Option<Status>,
+    sniff: bool,
+    ranges: bool,
+}
+
+/// How a user agent should present a file: the type of a `Content-Disposition`
+/// header.
+///
+/// The disposition of a [`NamedFile`] is set with [`NamedFile::inline()`],
+/// [`NamedFile::attachment()`], or [`NamedFile::with_disposition()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Disposition {
+    /// The file is displayed in the browser, if possible: `inline`.
+    Inline,
+    /// The file is downloaded and saved locally: `attachment`.
+    Attachment,
+}
+
+impl Disposition {
+    /// Returns the disposition type as it appears in a `Content-Disposition`
+    /// header.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::Disposition;
+    ///
+    /// assert_eq!(Disposition::Inline.as_str(), "inline");
+    /// assert_eq!(Disposition::Attachment.as_str(), "attachment");
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Disposition::Inline => "inline",
+            Disposition::Attachment => "attachment",
+        }
+    }
+}
+
+/// The metadata of a file needed to respond with it.
+#[derive(Debug, Clone)]
+struct FileInfo {
+    size: Option<u64>,
+    validators: Option<Validators>,
+}
+
+impl FileInfo {
+    fn from_metadata(metadata: &Metadata) -> FileInfo {
+        FileInfo {
+            size: known_size(metadata),
+            validators: Validators::from_metadata(metadata)
+        }
+    }
+}
 
 impl NamedFile {
+    /// The default size of the buffer used to read the file: 8KiB.
+    const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
     /// Attempts to open a file in read-only mode.
     ///
     /// # Errors
@@ -31,25 +102,238 @@ impl NamedFile {
     /// ```
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<NamedFile> {
         let file = File::open(path.as_ref())?;
-        Ok(NamedFile(path.as_ref().to_path_buf(), file))
+        let path = path.as_ref().to_path_buf();
+        let buffer_size = NamedFile::DEFAULT_BUFFER_SIZE;
+        let (info, download_name, disposition, status) = (None, None, None, None);
+        let (sniff, ranges) = (false, true);
+        Ok(NamedFile {
+            path, file, info, download_name, disposition, buffer_size, status, sniff, ranges
+        })
+    }
+
+    /// Attempts to open the file at the relative path `path` within the
+    /// directory `root` in read-only mode, ensuring that the file is inside of
+    /// `root`. Both paths are canonicalized, resolving symbolic links and `..`
+    /// segments, before checking that the file is contained in `root`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind
+    /// [`NotFound`](std::io::ErrorKind::NotFound) if the resolved file is not
+    /// contained in `root`, such as for a `path` of `../../etc/passwd`, or if
+    /// either path does not exist. Other errors may also be returned according
+    /// to [`OpenOptions::open()`](std::fs::OpenOptions::open()).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// # #[macro_use] extern crate rocket;
+    /// use std::path::PathBuf;
+    /// use rocket::response::NamedFile;
+    ///
+    /// #[get("/files/<path..>")]
+    /// fn files(path: PathBuf) -> Option<NamedFile> {
+    ///     NamedFile::open_in_root("static", path).ok()
+    /// }
+    /// ```
+    pub fn open_in_root<R, P>(root: R, path: P) -> io::Result<NamedFile>
+        where R: AsRef<Path>, P: AsRef<Path>
+    {
+        let root = root.as_ref().canonicalize()?;
+        let file = root.join(path.as_ref()).canonicalize()?;
+        if !file.starts_with(&root) {
+            let msg = "path escapes the root directory";
+            return Err(io::Error::new(io::ErrorKind::NotFound, msg));
+        }
+
+        NamedFile::open(file)
+    }
+
+    /// Attempts to open a file in read-only mode, giving up after `timeout`.
+    /// The file is opened on a separate thread so that opening a file on a
+    /// slow or unresponsive file system, such as a network mount, doesn't
+    /// block the caller indefinitely. If the timeout expires, the thread isn't
+    /// interrupted: it finishes opening the file in the background, and the
+    /// file is closed as soon as it is opened.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error of kind
+    /// [`TimedOut`](std::io::ErrorKind::TimedOut) if the file isn't opened
+    /// within `timeout`, which a handler may map to a **504 Gateway Timeout**.
+    /// Other errors are returned as for [`NamedFile::open()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open_timeout("/mnt/share/foo.txt", Duration::from_secs(2));
+    /// ```
+    pub fn open_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> io::Result<NamedFile> {
+        let path = path.as_ref().to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("rocket-file-open".into())
+            .spawn(move || { let _ = sender.send(NamedFile::open(path)); })?;
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "timed out opening the file"))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(io::Error::new(io::ErrorKind::Other, "the file opening thread panicked"))
+            }
+        }
+    }
+
+    /// Attempts to open a file in read-only mode, retrieving its metadata from
+    /// `cache`. The file system is only queried for the file's metadata if it
+    /// is not already in `cache`. See [`MetadataCache`] for details.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if path does not already exist or if
+    /// its metadata is not cached and cannot be retrieved. Other errors may
+    /// also be returned according to
+    /// [`OpenOptions::open()`](std::fs::OpenOptions::open()).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rocket::response::{NamedFile, MetadataCache};
+    ///
+    /// let cache = MetadataCache::new(128);
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open_cached("foo.txt", &cache);
+    /// ```
+    pub fn open_cached<P: AsRef<Path>>(path: P, cache: &MetadataCache) -> io::Result<NamedFile> {
+        NamedFile::open(path)?.cached(cache)
+    }
+
+    /// Retrieves the metadata of the already opened file from `cache`,
+    /// querying the file system only if it is not already in `cache`. This is
+    /// equivalent to opening the file with [`NamedFile::open_cached()`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file's metadata is not cached
+    /// and cannot be retrieved.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rocket::response::{NamedFile, MetadataCache};
+    ///
+    /// let cache = MetadataCache::new(128);
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("foo.txt").and_then(|file| file.cached(&cache));
+    /// ```
+    pub fn cached(mut self, cache: &MetadataCache) -> io::Result<NamedFile> {
+        let mut info = cache.get_or_insert(&self.path, &self.file)?;
+
+        // A file whose length changed has changed, so its cached validators
+        // and size are stale. Its length is found by seeking, which is cheaper
+        // than retrieving its metadata.
+        if let Some(size) = info.size {
+            let len = self.file.seek(io::SeekFrom::End(0))?;
+            self.file.seek(io::SeekFrom::Start(0))?;
+            if len != size {
+                cache.remove(&self.path);
+                info = cache.get_or_insert(&self.path, &self.file)?;
+            }
+        }
+
+        self.info = Some(info);
+        Ok(self)
+    }
+
+    /// Returns a responder for the `len` bytes of the open file `file` starting
+    /// at `offset`, served with the Content-Type `content_type`. This is
+    /// useful to serve a region of a larger file, such as an entry in an
+    /// archive. See [`FileSlice`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// # #[macro_use] extern crate rocket;
+    /// use std::fs::File;
+    /// use rocket::http::ContentType;
+    /// use rocket::response::{NamedFile, FileSlice};
+    ///
+    /// #[get("/logo.png")]
+    /// fn logo() -> Option<FileSlice> {
+    ///     let pack = File::open("assets.pack").ok()?;
+    ///     Some(NamedFile::from_file_range(pack, 4096, 1024, ContentType::PNG))
+    /// }
+    /// ```
+    pub fn from_file_range(
+        file: File,
+        offset: u64,
+        len: u64,
+        content_type: ContentType
+    ) -> FileSlice {
+        FileSlice::new(file, offset, len, content_type)
+    }
+
+    /// Attempts to read the entire file at `path` into memory, returning a
+    /// [`MemoryFile`] that responds without any further file system access.
+    /// The file's modification time, if available, is retained so that the
+    /// `MemoryFile` responds to conditional requests like a `NamedFile`.
+    ///
+    /// This is intended for small files that are served frequently. Changes to
+    /// the file after it has been read are not reflected in the `MemoryFile`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if path does not already exist or if
+    /// it cannot be read. Other errors may also be returned according to
+    /// [`OpenOptions::open()`](std::fs::OpenOptions::open()).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open_memory("foo.txt");
+    /// ```
+    pub fn open_memory<P: AsRef<Path>>(path: P) -> io::Result<MemoryFile> {
+        let mut file = File::open(path.as_ref())?;
+        let metadata = file.metadata()?;
+        let mut data = Vec::with_capacity(known_size(&metadata).unwrap_or(0) as usize);
+        file.read_to_end(&mut data)?;
+
+        let memory_file = MemoryFile::new(path, data);
+        Ok(match metadata.modified() {
+            Ok(modified) => memory_file.modified(modified),
+            Err(_) => memory_file
+        })
     }
 
     /// Retrieve the underlying `File`.
     #[inline(always)]
     pub fn file(&self) -> &File {
-        &self.1
+        &self.file
     }
 
     /// Take the underlying `File`.
     #[inline(always)]
     pub fn take_file(self) -> File {
-        self.1
+        self.file
     }
 
     /// Retrieve a mutable borrow to the underlying `File`.
     #[inline(always)]
     pub fn file_mut(&mut self) -> &mut File {
-        &mut self.1
+        &mut self.file
     }
 
     /// Retrieve the path of this file.
@@ -69,7 +353,426 @@ impl NamedFile {
     /// ```
     #[inline(always)]
     pub fn path(&self) -> &Path {
-        self.0.as_path()
+        self.path.as_path()
+    }
+
+    /// Evaluates the conditional request headers in `req` against the file's
+    /// [`Validators`]: its entity tag and last modification date. Returns
+    /// `None` if the file's validators cannot be determined. Otherwise, returns
+    /// the result of [`Validators::check()`].
+    ///
+    /// This is the same evaluation used by the `Responder` implementation of
+    /// `NamedFile`. It is exposed to allow handlers that modify files, such as
+    /// those for `PUT` or `DELETE` requests, to guard against lost updates.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::{Request, Data};
+    /// use rocket::handler::Outcome;
+    /// use rocket::response::NamedFile;
+    ///
+    /// fn replace<'r>(req: &'r Request, data: Data) -> Outcome<'r> {
+    ///     let file = match NamedFile::open("foo.txt") {
+    ///         Ok(file) => file,
+    ///         Err(_) => return Outcome::forward(data)
+    ///     };
+    ///
+    ///     if let Some(status) = file.check_preconditions(req) {
+    ///         return Outcome::failure(status);
+    ///     }
+    ///
+    ///     /* replace the contents of the file with `data` */
+    ///     # Outcome::from(req, ())
+    /// }
+    /// ```
+    pub fn check_preconditions(&self, req: &Request<'_>) -> Option<Status> {
+        self.info()?.validators?.check(req)
+    }
+
+    /// Marks the file as a download named `name`. The response then includes
+    /// a `Content-Disposition: attachment` header with the file name `name`,
+    /// and its `Content-Type` is derived from the extension of `name` rather
+    /// than that of the file's path. The file is still read from its path.
+    /// The disposition can be changed to `inline`, keeping the file name, with
+    /// [`NamedFile::inline()`].
+    ///
+    /// # Example
+    ///
+    /// Serve a file stored under an opaque name as `report.pdf`:
+    ///
+    /// ```rust
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("uploads/9b2e4f4c").map(|f| f.download_as("report.pdf"));
+    /// ```
+    pub fn download_as<S: Into<String>>(mut self, name: S) -> NamedFile {
+        self.download_name = Some(name.into());
+        self
+    }
+
+    /// Sets the disposition of the file to `disposition`. The response then
+    /// includes a `Content-Disposition` header of that type with the file name
+    /// set by [`NamedFile::download_as()`], if any. By default, there is no
+    /// `Content-Disposition` header, and browsers display the file inline,
+    /// unless a download name is set, in which case the file is an
+    /// attachment.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::{NamedFile, Disposition};
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("report.pdf")
+    ///     .map(|file| file.with_disposition(Disposition::Attachment));
+    /// ```
+    pub fn with_disposition(mut self, disposition: Disposition) -> NamedFile {
+        self.disposition = Some(disposition);
+        self
+    }
+
+    /// Displays the file in the browser. This is
+    /// [`with_disposition(Disposition::Inline)`](NamedFile::with_disposition()).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("report.pdf").map(|file| file.inline());
+    /// ```
+    pub fn inline(self) -> NamedFile {
+        self.with_disposition(Disposition::Inline)
+    }
+
+    /// Makes the browser download the file. This is
+    /// [`with_disposition(Disposition::Attachment)`](NamedFile::with_disposition()).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("report.pdf").map(|file| file.attachment());
+    /// ```
+    pub fn attachment(self) -> NamedFile {
+        self.with_disposition(Disposition::Attachment)
+    }
+
+    /// Sets the status of the response to `status`. By default, the status is
+    /// **200 OK** or determined by the request's conditional and range
+    /// headers. With a custom status, the entire file is always sent, and
+    /// neither conditional nor range requests are evaluated. This is useful
+    /// for serving error pages, such as a `404.html` file.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// # #[macro_use] extern crate rocket;
+    /// use rocket::Request;
+    /// use rocket::http::Status;
+    /// use rocket::response::NamedFile;
+    ///
+    /// #[catch(404)]
+    /// fn not_found(_: &Request) -> Option<NamedFile> {
+    ///     NamedFile::open("static/404.html").ok().map(|f| f.with_status(Status::NotFound))
+    /// }
+    /// ```
+    pub fn with_status(mut self, status: Status) -> NamedFile {
+        self.status = Some(status);
+        self
+    }
+
+    /// Sets whether byte range requests are honored to `enabled`. By default,
+    /// they are for files of known size. When disabled, the response includes
+    /// an `Accept-Ranges: none` header and `Range` headers are ignored: the
+    /// entire file is sent.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("report.txt").map(|file| file.accept_ranges(false));
+    /// ```
+    pub fn accept_ranges(mut self, enabled: bool) -> NamedFile {
+        self.ranges = enabled;
+        self
+    }
+
+    /// Determines the Content-Type of the response from the first bytes of
+    /// the file when the file's extension is absent or unrecognized. PNG,
+    /// JPEG, GIF, PDF, and gzip files are detected; the response to other
+    /// files has no Content-Type, as it would without sniffing. A recognized
+    /// extension always takes precedence. Only files of known size are
+    /// sniffed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #![feature(proc_macro_hygiene)]
+    /// # #[macro_use] extern crate rocket;
+    /// use std::path::PathBuf;
+    /// use rocket::response::NamedFile;
+    ///
+    /// #[get("/uploads/<id>")]
+    /// fn upload(id: usize) -> Option<NamedFile> {
+    ///     let path = PathBuf::from("uploads").join(id.to_string());
+    ///     NamedFile::open(path).ok().map(|file| file.sniff_content_type())
+    /// }
+    /// ```
+    pub fn sniff_content_type(mut self) -> NamedFile {
+        self.sniff = true;
+        self
+    }
+
+    /// Sets the size of the buffer used to read the file while responding to
+    /// `size` bytes. If the size of the file is unknown and its contents are
+    /// thus streamed, `size` is also the size of each chunk. The default is
+    /// 8KiB. Larger buffers reduce the number of reads needed to send large
+    /// files at the expense of memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is `0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("video.mp4").map(|f| f.buffer_size(64 * 1024));
+    /// ```
+    pub fn buffer_size(mut self, size: usize) -> NamedFile {
+        assert!(size > 0, "NamedFile buffer size must be non-zero");
+        self.buffer_size = size;
+        self
+    }
+
+    /// Returns the validators of the file, used to respond to conditional
+    /// requests, or `None` if they can't be determined.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::NamedFile;
+    ///
+    /// # #[allow(unused_variables)]
+    /// let validators = NamedFile::open("foo.txt").ok().and_then(|f| f.validators());
+    /// ```
+    pub fn validators(&self) -> Option<Validators> {
+        self.info()?.validators
+    }
+
+    /// Replaces the validators of the file with `validators`. The file is then
+    /// treated as having the entity tag and modification time of `validators`
+    /// when responding to conditional requests. This is useful when the
+    /// freshness of the file depends on other resources.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::SystemTime;
+    /// use rocket::response::{NamedFile, Validators};
+    ///
+    /// let validators = Validators::new(0, SystemTime::now());
+    /// # #[allow(unused_variables)]
+    /// let file = NamedFile::open("foo.txt").map(|f| f.with_validators(validators));
+    /// ```
+    pub fn with_validators(mut self, validators: Validators) -> NamedFile {
+        let size = self.info().and_then(|info| info.size);
+        self.info = Some(FileInfo { size, validators: Some(validators) });
+        self
+    }
+
+    /// Returns the cached file information if there is any. Otherwise, queries
+    /// the file system for it.
+    fn info(&self) -> Option<FileInfo> {
+        match self.info {
+            Some(ref info) => Some(info.clone()),
+            None => self.file.metadata().ok().map(|md| FileInfo::from_metadata(&md))
+        }
+    }
+}
+
+/// A least-recently-used cache of file metadata for [`NamedFile`]s.
+///
+/// Responding with a `NamedFile` requires the file's size and [`Validators`],
+/// which are ordinarily retrieved from the file system for each response. For
+/// files opened with [`NamedFile::open_cached()`], they are instead retrieved
+/// from a `MetadataCache`, avoiding the `stat` system call when a file is
+/// served repeatedly. The cache holds the metadata of up to `capacity` files;
+/// the least recently used entry is evicted when the cache is full.
+///
+/// When a file is opened with the cache, its current length is determined by
+/// seeking to its end, which doesn't require its metadata, and its entry is
+/// refreshed if the length differs from the cached size. Otherwise, cached
+/// entries are never revalidated by default: if a file is rewritten with
+/// contents of the same length after its metadata is cached, responses
+/// continue to carry the old validators until the entry is evicted. As such,
+/// a cache should only be used for files that don't change while being
+/// served. To bound how long such changes go unnoticed, set a time-to-live
+/// with [`MetadataCache::ttl()`]: entries older than it are refreshed on use.
+///
+/// # Example
+///
+/// A `MetadataCache` is typically shared between requests via managed state:
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// # #[macro_use] extern crate rocket;
+/// use std::path::PathBuf;
+/// use rocket::State;
+/// use rocket::response::{NamedFile, MetadataCache};
+///
+/// #[get("/tiles/<path..>")]
+/// fn tile(path: PathBuf, cache: State<MetadataCache>) -> Option<NamedFile> {
+///     NamedFile::open_cached(PathBuf::from("tiles").join(path), &cache).ok()
+/// }
+///
+/// fn main() {
+/// # if false {
+///     rocket::ignite()
+///         .manage(MetadataCache::new(1024))
+///         .mount("/", routes![tile])
+///         .launch();
+/// # }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MetadataCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    entries: Mutex<VecDeque<(PathBuf, FileInfo, Instant)>>,
+    /// The source of the current time, used to expire entries.
+    clock: fn() -> Instant,
+}
+
+impl MetadataCache {
+    /// Returns a new, empty cache that holds the metadata of up to `capacity`
+    /// files.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::MetadataCache;
+    ///
+    /// let cache = MetadataCache::new(128);
+    /// ```
+    pub fn new(capacity: usize) -> MetadataCache {
+        let entries = Mutex::new(VecDeque::with_capacity(capacity));
+        MetadataCache { capacity, ttl: None, entries, clock: Instant::now }
+    }
+
+    /// Sets the time-to-live of cached entries to `ttl`. An entry cached for
+    /// longer than `ttl` is refreshed from the file system when it is next
+    /// used.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use rocket::response::MetadataCache;
+    ///
+    /// let cache = MetadataCache::new(128).ttl(Duration::from_secs(5));
+    /// ```
+    pub fn ttl(mut self, ttl: Duration) -> MetadataCache {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the source of the current time, against which the age of entries
+    /// is measured, to `clock`. The default is [`Instant::now()`]. A clock that
+    /// is advanced by hand allows expiry to be tested without waiting.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::{Duration, Instant};
+    /// use rocket::response::MetadataCache;
+    ///
+    /// fn clock() -> Instant {
+    ///     Instant::now()
+    /// }
+    ///
+    /// let cache = MetadataCache::new(128).ttl(Duration::from_secs(5)).clock(clock);
+    /// ```
+    pub fn clock(mut self, clock: fn() -> Instant) -> MetadataCache {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the cached validators of the file at `path`, if its metadata
+    /// is cached and hasn't expired, marking it as most recently used. This
+    /// doesn't query the file system, so it can be used to answer conditional
+    /// requests without opening the file.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::MetadataCache;
+    ///
+    /// let cache = MetadataCache::new(128);
+    /// assert!(cache.validators("foo.txt").is_none());
+    /// ```
+    pub fn validators<P: AsRef<Path>>(&self, path: P) -> Option<Validators> {
+        let now = (self.clock)();
+        let mut entries = self.entries.lock().expect("MetadataCache lock");
+        let i = entries.iter().position(|(entry, ..)| entry == path.as_ref())?;
+        let entry = entries.remove(i).expect("valid index");
+        if self.ttl.map_or(false, |ttl| now.duration_since(entry.2) >= ttl) {
+            return None;
+        }
+
+        let validators = entry.1.validators.clone();
+        entries.push_front(entry);
+        validators
+    }
+
+    /// Removes the cached information for the file at `path`, if any.
+    fn remove(&self, path: &Path) {
+        let mut entries = self.entries.lock().expect("MetadataCache lock");
+        entries.retain(|(entry, ..)| entry != path);
+    }
+
+    /// Returns the cached information for the file at `path`, marking it as
+    /// most recently used. If there is none, retrieves it from `file`, which
+    /// must be the file at `path`, and caches it.
+    fn get_or_insert(&self, path: &Path, file: &File) -> io::Result<FileInfo> {
+        self.get_or_load(path, || Ok(FileInfo::from_metadata(&file.metadata()?)))
+    }
+
+    /// Returns the cached information for the file at `path`, marking it as
+    /// most recently used. If there is none or it has expired, retrieves it
+    /// with `load` and caches it.
+    fn get_or_load<F>(&self, path: &Path, load: F) -> io::Result<FileInfo>
+        where F: FnOnce() -> io::Result<FileInfo>
+    {
+        let now = (self.clock)();
+        let mut entries = self.entries.lock().expect("MetadataCache lock");
+        if let Some(i) = entries.iter().position(|(entry, ..)| entry == path) {
+            let entry = entries.remove(i).expect("valid index");
+            if self.ttl.map_or(true, |ttl| now.duration_since(entry.2) < ttl) {
+                let info = entry.1.clone();
+                entries.push_front(entry);
+                return Ok(info);
+            }
+        }
+
+        let info = load()?;
+        if self.capacity > 0 {
+            entries.truncate(self.capacity - 1);
+            entries.push_front((path.to_path_buf(), info.clone(), now));
+        }
+
+        Ok(info)
     }
 }
 
@@ -78,30 +781,442 @@ impl NamedFile {
 /// recognized. See [`ContentType::from_extension()`] for more information. If
 /// you would like to stream a file with a different Content-Type than that
 /// implied by its extension, use a [`File`] directly.
+///
+/// When the size of the file is known, the response carries a sized body and
+/// thus a `Content-Length`. This includes empty files, which are sent with a
+/// **200 OK** status and a `Content-Length` of `0`. Otherwise, such as for
+/// pipes, devices, or files on pseudo file systems like `procfs`, the body is
+/// sent using chunked transfer encoding.
+///
+/// The response includes `ETag` and `Last-Modified` headers. Conditional
+/// requests are evaluated as described in [`Validators::check()`]; when a
+/// precondition fails, the response has the returned status and no body.
+///
+/// If the size of the file is known and ranges haven't been disabled via
+/// [`NamedFile::accept_ranges()`], the response includes an `Accept-Ranges:
+/// bytes` header and byte range requests are honored with a **206 Partial
+/// Content** response, subject to any `If-Range` precondition. Requests for
+/// several ranges, up to 16, receive a `multipart/byteranges` body; requests
+/// for more ranges receive the entire file. Requests for only unsatisfiable
+/// ranges receive a **416 Range Not Satisfiable** response. Preconditions are
+/// evaluated before the range, so a failed precondition takes precedence.
+/// Otherwise, the response includes an `Accept-Ranges: none` header and byte
+/// range requests are ignored.
+///
+/// `HEAD` requests receive the same status and headers as the corresponding
+/// `GET` request, including the `Content-Range` and `Content-Length` of a
+/// partial response, without a body.
 impl Responder<'_> for NamedFile {
-    fn respond_to(self, req: &Request<'_>) -> response::Result<'static> {
-        let mut response = self.1.respond_to(req)?;
-        if let Some(ext) = self.0.extension() {
-            if let Some(ct) = ContentType::from_extension(&ext.to_string_lossy()) {
-                response.set_header(ct);
-            }
+    fn respond_to(mut self, req: &Request<'_>) -> response::Result<'static> {
+        let mut info = self.info().unwrap_or(FileInfo { size: None, validators: None });
+        if info.size.is_none() && is_empty_file(&mut self.file).unwrap_or(false) {
+            info.size = Some(0);
+        }
+
+        let name = self.download_name.as_ref().map(Path::new).unwrap_or(&self.path);
+        let known_type = name.extension()
+            .and_then(|ext| ContentType::from_extension(&ext.to_string_lossy()));
+
+        let sniffed = match self.sniff && known_type.is_none() && info.size.is_some() {
+            true => sniff(&mut self.file).map_err(|e| {
+                error_!("Failed to sniff Content-Type of file: {:?}", e);
+                Status::InternalServerError
+            })?,
+            false => None
+        };
+
+        let chunk_size = self.buffer_size as u64;
+        let body = BufReader::with_capacity(self.buffer_size, self.file);
+        let (size, validators) = (info.size, info.validators);
+        let options = ResponseOptions { status: self.status, ranges: self.ranges };
+        let mut response = respond_with(req, name, body, size, chunk_size, validators, options)?;
+        if let Some(content_type) = sniffed {
+            response.set_header(content_type);
+        }
+
+        let disposition = match (self.disposition, &self.download_name) {
+            (Some(disposition), _) => Some(disposition),
+            (None, Some(_)) => Some(Disposition::Attachment),
+            (None, None) => None
+        };
+
+        if let Some(disposition) = disposition {
+            let name = self.download_name.as_ref().map(String::as_str);
+            let value = content_disposition(disposition, name);
+            response.set_raw_header("Content-Disposition", value);
         }
 
         Ok(response)
     }
 }
 
+/// Returns the Content-Type indicated by the magic number at the start of
+/// `file`, if any, leaving `file` positioned at its start.
+fn sniff(file: &mut File) -> io::Result<Option<ContentType>> {
+    const SIGNATURES: &[(&[u8], ContentType)] = &[
+        (b"\x89PNG\r\n\x1a\n", ContentType::PNG),
+        (b"\xFF\xD8\xFF", ContentType::JPEG),
+        (b"GIF87a", ContentType::GIF),
+        (b"GIF89a", ContentType::GIF),
+        (b"%PDF-", ContentType::PDF),
+        (b"\x1F\x8B", ContentType::GZIP),
+    ];
+
+    let mut magic = Vec::with_capacity(8);
+    file.by_ref().take(8).read_to_end(&mut magic)?;
+    file.seek(io::SeekFrom::Start(0))?;
+
+    let content_type = SIGNATURES.iter()
+        .find(|(signature, _)| magic.starts_with(signature))
+        .map(|(_, content_type)| content_type.clone());
+
+    Ok(content_type)
+}
+
+/// Returns the value of a `Content-Disposition` header of type `disposition`
+/// for a file named `name`, if any. Per RFC 6266, an ASCII approximation of
+/// `name` is given in the `filename` parameter and, if `name` isn't plain
+/// ASCII, `name` itself is given in the RFC 5987 encoded `filename*`
+/// parameter.
+fn content_disposition(disposition: Disposition, name: Option<&str>) -> String {
+    let name = match name {
+        Some(name) => name,
+        None => return disposition.as_str().to_string()
+    };
+
+    let ascii_name: String = name.chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '_' })
+        .flat_map(|c| match c {
+            '"' | '\\' => vec!['\\', c],
+            _ => vec![c]
+        })
+        .collect();
+
+    let mut value = format!("{}; filename=\"{}\"", disposition.as_str(), ascii_name);
+    if name.chars().any(|c| !c.is_ascii() || c.is_ascii_control()) {
+        value.push_str("; filename*=UTF-8''");
+        for byte in name.bytes() {
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => value.push(byte as char),
+                b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.' | b'^' | b'_' | b'`'
+                    | b'|' | b'~' => value.push(byte as char),
+                _ => value.push_str(&format!("%{:02X}", byte))
+            }
+        }
+    }
+
+    value
+}
+
+/// Options for a file response built by [`respond_with()`].
+pub(crate) struct ResponseOptions {
+    /// The status of the response, if it is fixed.
+    pub status: Option<Status>,
+    /// Whether byte range requests are honored.
+    pub ranges: bool,
+}
+
+impl Default for ResponseOptions {
+    fn default() -> Self {
+        ResponseOptions { status: None, ranges: true }
+    }
+}
+
+/// Responds with `body`, the contents of the file at `path` of `size` bytes, if
+/// known, with validators `validators`, if known. This evaluates conditional
+/// and range requests and sets the `Content-Type` based on the extension of
+/// `path`. If the size of `body` is known, at most `size` bytes are sent.
+/// Otherwise, `body` is streamed in chunks of `chunk_size` bytes.
+///
+/// If `options.status` is `Some`, the entire body is sent with that status
+/// instead: conditional and range requests are not evaluated, and neither
+/// validators nor `Accept-Ranges` are sent. If `options.ranges` is `false`,
+/// range requests are not evaluated, and `Accept-Ranges: none` is sent.
+pub(crate) fn respond_with<'r, B: Read + Seek + 'r>(
+    req: &Request<'_>,
+    path: &Path,
+    mut body: B,
+    size: Option<u64>,
+    chunk_size: u64,
+    validators: Option<Validators>,
+    options: ResponseOptions,
+) -> response::Result<'r> {
+    let content_type = path.extension()
+        .and_then(|ext| ContentType::from_extension(&ext.to_string_lossy()));
+
+    let mut response = Response::new();
+    if let Some(ref ct) = content_type {
+        response.set_header(ct.clone());
+    }
+
+    if let Some(status) = options.status {
+        CacheDecision::Sent.record(req);
+        response.set_status(status);
+        match size {
+            Some(size) => response.set_raw_body(Body::Sized(body, size)),
+            None => response.set_chunked_body(body, chunk_size)
+        }
+
+        return Ok(response);
+    }
+
+    let status = validators.as_ref().and_then(|v| v.check(req));
+    match status {
+        Some(Status::NotModified) => CacheDecision::NotModified.record(req),
+        Some(_) => CacheDecision::PreconditionFailed.record(req),
+        None => CacheDecision::Sent.record(req),
+    }
+
+    if let Some(status) = status {
+        response.set_status(status);
+    } else if let Some(size) = size.filter(|_| options.ranges) {
+        response.set_raw_header("Accept-Ranges", "bytes");
+        match ByteRange::from_request(req, size, validators.as_ref()) {
+            ByteRange::Full => response.set_raw_body(Body::Sized(body, size)),
+            ByteRange::Partial(start, end) => {
+                body.seek(io::SeekFrom::Start(start)).map_err(|e| {
+                    error_!("Failed to seek to start of range: {:?}", e);
+                    Status::InternalServerError
+                })?;
+
+                let len = end - start + 1;
+                let range = Some((start, end));
+                response.set_status(Status::PartialContent);
+                response.set_header(ContentRange(Bytes { range, instance_length: Some(size) }));
+                response.set_raw_body(Body::Sized(body, len));
+            }
+            ByteRange::Multiple(ranges) => {
+                let boundary = boundary(size, &ranges);
+                let ranges = ByteRanges::new(body, size, &ranges, content_type.as_ref(), &boundary);
+                let media_type = format!("multipart/byteranges; boundary={}", boundary);
+                let len = ranges.len();
+                response.set_status(Status::PartialContent);
+                response.set_raw_header("Content-Type", media_type);
+                response.set_raw_body(Body::Sized(ranges, len));
+            }
+            ByteRange::Unsatisfiable => {
+                response.set_status(Status::RangeNotSatisfiable);
+                response.set_header(ContentRange(Bytes { range: None, instance_length: Some(size) }));
+            }
+        }
+    } else if let Some(size) = size {
+        response.set_raw_header("Accept-Ranges", "none");
+        response.set_raw_body(Body::Sized(body, size));
+    } else {
+        response.set_raw_header("Accept-Ranges", "none");
+        response.set_chunked_body(body, chunk_size);
+    }
+
+    if let Some(validators) = validators {
+        validators.set_headers(&mut response);
+    }
+
+    Ok(response)
+}
+
+/// The part of a body of known size requested by a `Range` header.
+#[derive(Debug, Clone, PartialEq)]
+enum ByteRange {
+    /// The entire body.
+    Full,
+    /// The bytes from the first to the second offset, inclusive.
+    Partial(u64, u64),
+    /// Several ranges of bytes, each inclusive, in the requested order.
+    Multiple(Vec<(u64, u64)>),
+    /// No range overlaps the body.
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// The maximum number of ranges served in one response. Requests for more
+    /// ranges receive the entire body.
+    const MAX_RANGES: usize = 16;
+
+    /// Determines the range of a body of `size` bytes with `validators`
+    /// requested by `req`. Invalid `Range` headers, those for units other than
+    /// bytes or for more than `MAX_RANGES` ranges, and those failing an
+    /// `If-Range` precondition are ignored; the entire body is requested.
+    fn from_request(req: &Request<'_>, size: u64, validators: Option<&Validators>) -> ByteRange {
+        let range = match req.headers().get_one("Range") {
+            Some(range) => range.trim(),
+            None => return ByteRange::Full
+        };
+
+        if let Some(if_range) = req.headers().get_one("If-Range") {
+            if !validators.map_or(false, |v| v.matches_if_range(if_range)) {
+                return ByteRange::Full;
+            }
+        }
+
+        if !range.starts_with("bytes=") {
+            return ByteRange::Full;
+        }
+
+        // Unsatisfiable ranges are skipped as long as another is satisfiable.
+        let mut ranges = vec![];
+        for spec in range["bytes=".len()..].split(',') {
+            match ByteRange::parse(spec.trim(), size) {
+                Some(ByteRange::Partial(start, end)) => ranges.push((start, end)),
+                Some(_) => continue,
+                None => return ByteRange::Full
+            }
+        }
+
+        match ranges.len() {
+            0 => ByteRange::Unsatisfiable,
+            1 => ByteRange::Partial(ranges[0].0, ranges[0].1),
+            n if n > Self::MAX_RANGES => ByteRange::Full,
+            _ => ByteRange::Multiple(ranges)
+        }
+    }
+
+    /// Parses a single byte range specifier, `spec`, for a body of `size`
+    /// bytes into a `Partial` or `Unsatisfiable` range. Returns `None` if
+    /// `spec` is invalid.
+    fn parse(spec: &str, size: u64) -> Option<ByteRange> {
+        let dash = spec.find('-')?;
+        let (first, last) = (spec[..dash].trim(), spec[dash + 1..].trim());
+        if first.is_empty() {
+            // A suffix range: the last `n` bytes of the body.
+            return match last.parse::<u64>().ok()? {
+                n if n == 0 || size == 0 => Some(ByteRange::Unsatisfiable),
+                n => Some(ByteRange::Partial(size.saturating_sub(n), size - 1))
+            };
+        }
+
+        let start = first.parse::<u64>().ok()?;
+        let end = match last.is_empty() {
+            true => None,
+            false => Some(last.parse::<u64>().ok()?)
+        };
+
+        match end {
+            Some(end) if end < start => None,
+            _ if start >= size => Some(ByteRange::Unsatisfiable),
+            Some(end) => Some(ByteRange::Partial(start, std::cmp::min(end, size - 1))),
+            None => Some(ByteRange::Partial(start, size - 1))
+        }
+    }
+}
+
+/// A `multipart/byteranges` body: the requested ranges of a body, each
+/// preceded by a boundary and its `Content-Type` and `Content-Range` headers,
+/// followed by a closing boundary.
+struct ByteRanges<B> {
+    body: B,
+    parts: VecDeque<Part>,
+    len: u64,
+}
+
+/// A part of a `multipart/byteranges` body.
+enum Part {
+    /// Boundaries and headers.
+    Literal(io::Cursor<Vec<u8>>),
+    /// The remaining `len` bytes of a range starting at offset `start` and
+    /// whether the body has been positioned at `start`.
+    Range { start: u64, len: u64, positioned: bool },
+}
+
+impl<B: Read + Seek> ByteRanges<B> {
+    /// Returns the `ranges` of `body`, of `size` bytes, separated by
+    /// `boundary`. Each part has the content type `content_type`, if any.
+    fn new(
+        body: B,
+        size: u64,
+        ranges: &[(u64, u64)],
+        content_type: Option<&ContentType>,
+        boundary: &str
+    ) -> ByteRanges<B> {
+        let mut parts = VecDeque::with_capacity(2 * ranges.len() + 1);
+        for (i, &(start, end)) in ranges.iter().enumerate() {
+            let mut headers = match i {
+                0 => format!("--{}\r\n", boundary),
+                _ => format!("\r\n--{}\r\n", boundary)
+            };
+
+            if let Some(content_type) = content_type {
+                headers.push_str(&format!("Content-Type: {}\r\n", content_type));
+            }
+
+            headers.push_str(&format!("Content-Range: bytes {}-{}/{}\r\n\r\n", start, end, size));
+            parts.push_back(Part::Literal(io::Cursor::new(headers.into_bytes())));
+            parts.push_back(Part::Range { start, len: end - start + 1, positioned: false });
+        }
+
+        let closing = format!("\r\n--{}--\r\n", boundary);
+        parts.push_back(Part::Literal(io::Cursor::new(closing.into_bytes())));
+
+        let len = parts.iter()
+            .map(|part| match part {
+                Part::Literal(cursor) => cursor.get_ref().len() as u64,
+                Part::Range { len, .. } => *len
+            })
+            .sum();
+
+        ByteRanges { body, parts, len }
+    }
+
+    /// The total length of the body in bytes.
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+/// Returns a `multipart/byteranges` boundary for the `ranges` of a body of
+/// `size` bytes.
+fn boundary(size: u64, ranges: &[(u64, u64)]) -> String {
+    let mut hasher = DefaultHasher::new();
+    (size, ranges, SystemTime::now()).hash(&mut hasher);
+    format!("rocket-byteranges-{:016x}", hasher.finish())
+}
+
+impl<B: Read + Seek> Read for ByteRanges<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let n = match self.parts.front_mut() {
+                None => return Ok(0),
+                Some(Part::Literal(cursor)) => cursor.read(buf)?,
+                Some(Part::Range { start, len, positioned }) => {
+                    if !*positioned {
+                        self.body.seek(io::SeekFrom::Start(*start))?;
+                        *positioned = true;
+                    }
+
+                    let max = std::cmp::min(buf.len() as u64, *len) as usize;
+                    let n = self.body.read(&mut buf[..max])?;
+                    if n == 0 && max > 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+
+                    *len -= n as u64;
+                    n
+                }
+            };
+
+            if n > 0 {
+                return Ok(n);
+            }
+
+            self.parts.pop_front();
+        }
+    }
+}
+
 impl Deref for NamedFile {
     type Target = File;
 
     fn deref(&self) -> &File {
-        &self.1
+        &self.file
     }
 }
 
 impl DerefMut for NamedFile {
     fn deref_mut(&mut self) -> &mut File {
-        &mut self.1
+        &mut self.file
     }
 }
 
@@ -156,3 +1271,98 @@ impl io::Seek for &NamedFile {
         self.file().seek(pos)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::path::Path;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use super::{FileInfo, MetadataCache};
+    use crate::response::Validators;
+
+    thread_local! {
+        static START: Instant = Instant::now();
+        static ELAPSED: Cell<Duration> = Cell::new(Duration::from_secs(0));
+    }
+
+    /// A clock that only advances when `advance()` is called.
+    fn clock() -> Instant {
+        START.with(|start| *start + ELAPSED.with(|elapsed| elapsed.get()))
+    }
+
+    fn advance(by: Duration) {
+        ELAPSED.with(|elapsed| elapsed.set(elapsed.get() + by));
+    }
+
+    fn info(size: u64, mtime: u64) -> FileInfo {
+        let modified = UNIX_EPOCH + Duration::from_secs(mtime);
+        FileInfo { size: Some(size), validators: Some(Validators::new(size, modified)) }
+    }
+
+    fn modified(info: &FileInfo) -> Option<SystemTime> {
+        info.validators.as_ref().map(|validators| validators.last_modified())
+    }
+
+    #[test]
+    fn test_metadata_cache_expires_stale_entries() {
+        let cache = MetadataCache::new(8).ttl(Duration::from_secs(30)).clock(clock);
+
+        let path = Path::new("static/app.js");
+        let cached = cache.get_or_load(path, || Ok(info(10, 1_000))).unwrap();
+        assert_eq!(modified(&cached), Some(UNIX_EPOCH + Duration::from_secs(1_000)));
+
+        // Until the entry expires, the file's new metadata isn't observed.
+        advance(Duration::from_secs(29));
+        let cached = cache.get_or_load(path, || Ok(info(20, 2_000))).unwrap();
+        assert_eq!(cached.size, Some(10));
+        assert_eq!(modified(&cached), Some(UNIX_EPOCH + Duration::from_secs(1_000)));
+
+        // Once it has, the entry is refreshed and its age is reset.
+        advance(Duration::from_secs(1));
+        let cached = cache.get_or_load(path, || Ok(info(20, 2_000))).unwrap();
+        assert_eq!(cached.size, Some(20));
+        assert_eq!(modified(&cached), Some(UNIX_EPOCH + Duration::from_secs(2_000)));
+
+        advance(Duration::from_secs(29));
+        let cached = cache.get_or_load(path, || Ok(info(30, 3_000))).unwrap();
+        assert_eq!(cached.size, Some(20));
+    }
+
+    #[test]
+    fn test_metadata_cache_validators_respect_ttl() {
+        let cache = MetadataCache::new(8).ttl(Duration::from_secs(30)).clock(clock);
+
+        let path = Path::new("static/logo.png");
+        assert!(cache.validators(path).is_none());
+
+        let cached = cache.get_or_load(path, || Ok(info(10, 1_000))).unwrap();
+        assert_eq!(cache.validators(path), cached.validators);
+
+        advance(Duration::from_secs(30));
+        assert!(cache.validators(path).is_none());
+    }
+
+    #[test]
+    fn test_metadata_cache_loads_once() {
+        let cache = MetadataCache::new(8);
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(info(10, 1_000))
+        };
+
+        let path = Path::new("static/tile.png");
+        let first = cache.get_or_load(path, load).unwrap();
+        for _ in 0..10 {
+            let cached = cache.get_or_load(path, load).unwrap();
+            assert_eq!(cached.validators, first.validators);
+        }
+
+        assert_eq!(loads.get(), 1);
+
+        cache.remove(path);
+        cache.get_or_load(path, load).unwrap();
+        assert_eq!(loads.get(), 2);
+    }
+}
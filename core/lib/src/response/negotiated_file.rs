@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use crate::request::Request;
+use crate::response::{self, NamedFile, Responder};
+use crate::http::{Accept, MediaType, Status};
+
+/// A file chosen among siblings by the request's `Accept` header.
+///
+/// A `NegotiatedFile` is created with a base path, such as `docs/data`, and a
+/// list of extensions, such as `html` and `json`. When responding, the
+/// extension whose media type is most preferred by the request's `Accept`
+/// header is appended to the base path, and the resulting file, such as
+/// `docs/data.json`, is served as a [`NamedFile`]. If the `Accept` header is
+/// missing or matches none of the extensions, the first extension is used.
+///
+/// The response includes a `Vary: Accept` header. If the chosen file doesn't
+/// exist, the response fails with a **404 Not Found**.
+///
+/// # Example
+///
+/// ```rust
+/// # #![feature(proc_macro_hygiene)]
+/// # #[macro_use] extern crate rocket;
+/// use rocket::response::NegotiatedFile;
+///
+/// #[get("/data")]
+/// fn data() -> NegotiatedFile {
+///     NegotiatedFile::new("static/data", &["html", "json"])
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NegotiatedFile {
+    base: PathBuf,
+    extensions: Vec<String>,
+}
+
+impl NegotiatedFile {
+    /// Returns a file with base path `base` available with the extensions in
+    /// `extensions`. The first extension is the default.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rocket::response::NegotiatedFile;
+    ///
+    /// let file = NegotiatedFile::new("data", &["html", "json"]);
+    /// ```
+    pub fn new<P, I, S>(base: P, extensions: I) -> NegotiatedFile
+        where P: AsRef<Path>, I: IntoIterator<Item = S>, S: AsRef<str>
+    {
+        let extensions = extensions.into_iter().map(|ext| ext.as_ref().to_string()).collect();
+        NegotiatedFile { base: base.as_ref().to_path_buf(), extensions }
+    }
+
+    /// Returns the path of the file to serve for a request with the `Accept`
+    /// header `accept`, if any.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use rocket::http::Accept;
+    /// use rocket::response::NegotiatedFile;
+    ///
+    /// let file = NegotiatedFile::new("data", &["html", "json"]);
+    /// assert_eq!(file.path_for(Some(&Accept::JSON)), Path::new("data.json"));
+    /// assert_eq!(file.path_for(Some(&Accept::XML)), Path::new("data.html"));
+    /// assert_eq!(file.path_for(None), Path::new("data.html"));
+    /// ```
+    pub fn path_for(&self, accept: Option<&Accept>) -> PathBuf {
+        let mut preferences: Vec<_> = accept.into_iter()
+            .flat_map(|accept| accept.iter())
+            .filter(|media_type| media_type.weight_or(1.0) > 0.0)
+            .collect();
+
+        // Sort by decreasing weight, keeping the header's order for ties.
+        preferences.sort_by(|a, b| {
+            b.weight_or(1.0).partial_cmp(&a.weight_or(1.0)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let extension = preferences.iter()
+            .filter_map(|accepted| self.extensions.iter().find(|ext| {
+                MediaType::from_extension(ext)
+                    .map_or(false, |media_type| matches(accepted.media_type(), &media_type))
+            }))
+            .next()
+            .or_else(|| self.extensions.first());
+
+        let mut path = self.base.clone().into_os_string();
+        if let Some(extension) = extension {
+            path.push(".");
+            path.push(extension);
+        }
+
+        path.into()
+    }
+}
+
+/// Returns `true` if `media_type` is matched by the possibly wildcard media
+/// type `accepted`.
+fn matches(accepted: &MediaType, media_type: &MediaType) -> bool {
+    (accepted.top() == "*" || accepted.top() == media_type.top())
+        && (accepted.sub() == "*" || accepted.sub() == media_type.sub())
+}
+
+/// Serves the file chosen by [`NegotiatedFile::path_for()`] as a
+/// [`NamedFile`] with an additional `Vary: Accept` header. Fails with a **404
+/// Not Found** if the file can't be opened.
+impl Responder<'_> for NegotiatedFile {
+    fn respond_to(self, req: &Request<'_>) -> response::Result<'static> {
+        let path = self.path_for(req.accept());
+        let file = NamedFile::open(&path).map_err(|e| {
+            warn_!("Failed to open negotiated file {:?}: {:?}", path, e);
+            Status::NotFound
+        })?;
+
+        let mut response = file.respond_to(req)?;
+        response.set_raw_header("Vary", "Accept");
+        Ok(response)
+    }
+}
@@ -1,5 +1,5 @@
-use std::fs::File;
-use std::io::{Cursor, BufReader};
+use std::fs::{File, Metadata};
+use std::io::{self, Cursor, BufReader, Read, Seek};
 
 use crate::http::{Status, ContentType, StatusClass};
 use crate::response::{self, Response, Body};
@@ -67,9 +67,11 @@ use crate::request::Request;
 ///
 ///   * **File**
 ///
-///     Responds with a streamed body containing the data in the `File`. No
-///     `Content-Type` is set. To automatically have a `Content-Type` set based
-///     on the file's extension, use [`NamedFile`](crate::response::NamedFile).
+///     Responds with a streamed body containing the data in the `File`. If the
+///     size of the file is known, the body is sized; otherwise, the body is
+///     chunked. No `Content-Type` is set. To automatically have a
+///     `Content-Type` set based on the file's extension, use
+///     [`NamedFile`](crate::response::NamedFile).
 ///
 ///   * **()**
 ///
@@ -238,17 +240,51 @@ impl Responder<'_> for Vec<u8> {
     }
 }
 
-/// Returns a response with a sized body for the file. Always returns `Ok`.
+/// Returns a response with a sized body for the file if its size is known and a
+/// chunked body otherwise. Empty regular files have a known size of `0`.
+/// Always returns `Ok`.
 impl Responder<'_> for File {
-    fn respond_to(self, _: &Request<'_>) -> response::Result<'static> {
-        let (metadata, file) = (self.metadata(), BufReader::new(self));
-        match metadata {
-            Ok(md) => Response::build().raw_body(Body::Sized(file, md.len())).ok(),
-            Err(_) => Response::build().streamed_body(file).ok()
+    fn respond_to(mut self, _: &Request<'_>) -> response::Result<'static> {
+        let mut size = self.metadata().ok().and_then(|md| known_size(&md));
+        if size.is_none() && is_empty_file(&mut self).unwrap_or(false) {
+            size = Some(0);
+        }
+
+        let file = BufReader::new(self);
+        match size {
+            Some(size) => Response::build().raw_body(Body::Sized(file, size)).ok(),
+            None => Response::build().streamed_body(file).ok()
         }
     }
 }
 
+/// Returns the size of the file described by `metadata` if it can be trusted.
+///
+/// Only regular files have a meaningful length. Pipes, sockets, and devices
+/// don't, and files on pseudo file systems like `procfs` report a length of `0`
+/// even though reading them yields data. The size of all of these is unknown
+/// until they are read in full.
+pub(crate) fn known_size(metadata: &Metadata) -> Option<u64> {
+    match metadata.len() {
+        len if metadata.is_file() && len > 0 => Some(len),
+        _ => None
+    }
+}
+
+/// Returns `true` if `file` is a regular file without contents, leaving it
+/// positioned at its start. Files on pseudo file systems like `procfs` also
+/// report a length of `0`, so only reading from the file tells them apart.
+pub(crate) fn is_empty_file(file: &mut File) -> io::Result<bool> {
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || metadata.len() != 0 {
+        return Ok(false);
+    }
+
+    let empty = file.read(&mut [0u8])? == 0;
+    file.seek(io::SeekFrom::Start(0))?;
+    Ok(empty)
+}
+
 /// Returns an empty, default `Response`. Always returns `Ok`.
 impl Responder<'_> for () {
     fn respond_to(self, _: &Request<'_>) -> response::Result<'static> {
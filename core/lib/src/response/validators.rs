@@ -0,0 +1,312 @@
+use std::fs::Metadata;
+use std::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::request::Request;
+use crate::response::Response;
+use crate::http::{Method, Status};
+use crate::http::hyper::header::{ETag, EntityTag, HttpDate, LastModified};
+
+/// The validators of a file against which conditional requests are evaluated.
+///
+/// A file's validators consist of a strong entity tag, derived from the file's
+/// length and modification time, and the file's last modification date with
+/// one second resolution. [`NamedFile`](crate::response::NamedFile) uses
+/// `Validators` to respond to conditional requests. They are exposed to allow
+/// other responders for file-like data, such as files embedded in a binary,
+/// to do the same.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use rocket::response::Validators;
+///
+/// let modified = UNIX_EPOCH + Duration::from_millis(1_500);
+/// let validators = Validators::new(42, modified);
+/// assert_eq!(validators.last_modified(), UNIX_EPOCH + Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validators {
+    etag: EntityTag,
+    /// The modification time in whole seconds since the Unix epoch.
+    last_modified: i64,
+}
+
+impl Validators {
+    /// Returns the validators for a file of length `len` last modified at
+    /// `modified`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::SystemTime;
+    /// use rocket::response::Validators;
+    ///
+    /// let validators = Validators::new(1024, SystemTime::now());
+    /// ```
+    pub fn new(len: u64, modified: SystemTime) -> Validators {
+        let max = i64::max_value() as u64;
+        let last_modified = match modified.duration_since(UNIX_EPOCH) {
+            Ok(duration) => std::cmp::min(duration.as_secs(), max) as i64,
+            Err(e) => -(std::cmp::min(e.duration().as_secs(), max) as i64),
+        };
+
+        let tag = format!("{:x}-{:x}", last_modified, len);
+        Validators { etag: EntityTag::new(false, tag), last_modified }
+    }
+
+    /// Returns the validators for the file with metadata `metadata` or `None`
+    /// if the file's modification time is unavailable.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io;
+    /// use std::fs::File;
+    /// use rocket::response::Validators;
+    ///
+    /// # #[allow(dead_code)]
+    /// # fn demo() -> io::Result<()> {
+    /// let metadata = File::open("foo.txt")?.metadata()?;
+    /// let validators = Validators::from_metadata(&metadata);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_metadata(metadata: &Metadata) -> Option<Validators> {
+        Some(Validators::new(metadata.len(), metadata.modified().ok()?))
+    }
+
+    /// Returns validators for a resource composed of the resources with
+    /// validators `validators`, or `None` if `validators` is empty.
+    ///
+    /// The last modification time of the composite is the latest of those in
+    /// `validators`; its entity tag changes whenever any of the entity tags in
+    /// `validators` does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use rocket::response::Validators;
+    ///
+    /// let page = Validators::new(512, UNIX_EPOCH + Duration::from_secs(10));
+    /// let script = Validators::new(2048, UNIX_EPOCH + Duration::from_secs(20));
+    /// let composite = Validators::combine(vec![page, script]).unwrap();
+    /// assert_eq!(composite.last_modified(), UNIX_EPOCH + Duration::from_secs(20));
+    /// ```
+    pub fn combine<I: IntoIterator<Item = Validators>>(validators: I) -> Option<Validators> {
+        let mut hasher = DefaultHasher::new();
+        let mut last_modified = None;
+        for validators in validators {
+            validators.etag.tag().hash(&mut hasher);
+            last_modified = std::cmp::max(last_modified, Some(validators.last_modified));
+        }
+
+        let etag = EntityTag::new(false, format!("{:x}", hasher.finish()));
+        Some(Validators { etag, last_modified: last_modified? })
+    }
+
+    /// Returns the strong entity tag.
+    #[inline(always)]
+    pub fn etag(&self) -> &EntityTag {
+        &self.etag
+    }
+
+    /// Returns the last modification time truncated to whole seconds.
+    pub fn last_modified(&self) -> SystemTime {
+        let time = match self.last_modified {
+            secs if secs >= 0 => UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64)),
+            secs => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.wrapping_neg() as u64)),
+        };
+
+        time.unwrap_or(UNIX_EPOCH)
+    }
+
+    /// Returns the last modification date or `None` if it can't be expressed
+    /// as an HTTP date, whose year has four digits.
+    fn last_modified_date(&self) -> Option<HttpDate> {
+        // The first and last second of the years 1 through 9999.
+        const MIN: i64 = -62_135_596_800;
+        const MAX: i64 = 253_402_300_799;
+
+        match self.last_modified {
+            secs if secs < MIN || secs > MAX => None,
+            secs => Some(HttpDate(time::at_utc(time::Timespec::new(secs, 0))))
+        }
+    }
+
+    /// Evaluates the conditional request headers in `req` against `self`.
+    ///
+    /// Returns `Some(Status::PreconditionFailed)` if an `If-Match` or
+    /// `If-Unmodified-Since` precondition fails or if an `If-None-Match`
+    /// precondition fails for a method other than `GET` or `HEAD`. Returns
+    /// `Some(Status::NotModified)` if an `If-None-Match` or `If-Modified-Since`
+    /// precondition fails for a `GET` or `HEAD` request. Otherwise, returns
+    /// `None`.
+    ///
+    /// Preconditions are evaluated in the order prescribed by [RFC 7232
+    /// §6](https://tools.ietf.org/html/rfc7232#section-6). In particular, a
+    /// date precondition is ignored when the corresponding entity tag
+    /// precondition is present: `If-Unmodified-Since` when there is an
+    /// `If-Match` header, and `If-Modified-Since` when there is an
+    /// `If-None-Match` header. Invalid dates are ignored.
+    pub fn check(&self, req: &Request<'_>) -> Option<Status> {
+        let safe_method = match req.method() {
+            Method::Get | Method::Head => true,
+            _ => false
+        };
+
+        if let Some(tags) = TagList::from_request(req, "If-Match") {
+            if !tags.matches(|tag| tag.strong_eq(&self.etag)) {
+                return Some(Status::PreconditionFailed);
+            }
+        } else if let Some(date) = parse_date(req, "If-Unmodified-Since") {
+            if self.last_modified > date {
+                return Some(Status::PreconditionFailed);
+            }
+        }
+
+        if let Some(tags) = TagList::from_request(req, "If-None-Match") {
+            if tags.matches(|tag| tag.weak_eq(&self.etag)) {
+                return match safe_method {
+                    true => Some(Status::NotModified),
+                    false => Some(Status::PreconditionFailed),
+                };
+            }
+        } else if let Some(date) = parse_date(req, "If-Modified-Since") {
+            if safe_method && self.last_modified <= date {
+                return Some(Status::NotModified);
+            }
+        }
+
+        None
+    }
+
+    /// Returns `true` if the value of an `If-Range` header, `if_range`,
+    /// matches `self`. An entity tag matches if it is strongly equal to the
+    /// entity tag of `self`; a date matches if it is exactly the last
+    /// modification date of `self`.
+    pub(crate) fn matches_if_range(&self, if_range: &str) -> bool {
+        let if_range = if_range.trim();
+        if if_range.starts_with('"') || if_range.starts_with("W/") {
+            if_range.parse::<EntityTag>().map_or(false, |tag| tag.strong_eq(&self.etag))
+        } else {
+            if_range.parse::<HttpDate>()
+                .map_or(false, |date| date.0.to_timespec().sec == self.last_modified)
+        }
+    }
+
+    /// Sets the `ETag` and `Last-Modified` headers in `response`, overriding
+    /// any existing values. The `Last-Modified` header is omitted if the last
+    /// modification date can't be expressed as an HTTP date, which requires a
+    /// year between 1 and 9999.
+    pub fn set_headers(&self, response: &mut Response<'_>) {
+        match self.last_modified_date() {
+            Some(date) => response.set_header(LastModified(date)),
+            None => warn_!("Omitting out-of-range Last-Modified time: {}s.", self.last_modified)
+        }
+
+        response.set_header(ETag(self.etag.clone()));
+    }
+}
+
+/// The value of an `If-Match` or `If-None-Match` header.
+enum TagList {
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl TagList {
+    fn from_request(req: &Request<'_>, name: &str) -> Option<TagList> {
+        let mut values = req.headers().get(name).peekable();
+        values.peek()?;
+
+        let mut tags = vec![];
+        for value in values.flat_map(|value| value.split(',')).map(|v| v.trim()) {
+            match value {
+                "*" => return Some(TagList::Any),
+                _ => tags.extend(value.parse::<EntityTag>().ok())
+            }
+        }
+
+        Some(TagList::Tags(tags))
+    }
+
+    fn matches<F: Fn(&EntityTag) -> bool>(&self, f: F) -> bool {
+        match self {
+            TagList::Any => true,
+            TagList::Tags(tags) => tags.iter().any(f)
+        }
+    }
+}
+
+/// Parses the HTTP date in the header `name` into seconds since the Unix epoch.
+/// Returns `None` if the header is missing or invalid, in which case it must be
+/// ignored.
+fn parse_date(req: &Request<'_>, name: &str) -> Option<i64> {
+    let date = req.headers().get_one(name)?.parse::<HttpDate>().ok()?;
+    Some(date.0.to_timespec().sec)
+}
+
+/// The outcome of evaluating a conditional request for a file.
+///
+/// [`NamedFile`](crate::response::NamedFile) and
+/// [`MemoryFile`](crate::response::MemoryFile) record the decision they make
+/// when responding in the request-local cache, where it can be retrieved with
+/// [`CacheDecision::of()`]. This allows a fairing to count how often clients'
+/// cached copies are reused without re-evaluating the request's preconditions.
+///
+/// # Example
+///
+/// Log the decision for every file response:
+///
+/// ```rust
+/// use rocket::fairing::AdHoc;
+/// use rocket::response::CacheDecision;
+///
+/// let fairing = AdHoc::on_response("Cache Metrics", |req, _| {
+///     if let Some(decision) = CacheDecision::of(req) {
+///         println!("{}: {:?}", req.uri(), decision);
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheDecision {
+    /// The client's copy is current: **304 Not Modified** was returned.
+    NotModified,
+    /// A precondition failed: **412 Precondition Failed** was returned.
+    PreconditionFailed,
+    /// The file, or a range of it, was returned.
+    Sent,
+}
+
+/// The request-local cache entry holding a request's `CacheDecision`. It is
+/// created by whichever of `of()` and `record()` is called first, so the
+/// decision is set in place.
+struct CachedDecision(Mutex<Option<CacheDecision>>);
+
+impl CacheDecision {
+    /// Returns the decision made when responding to `req` with a file, or
+    /// `None` if no file response has been generated for `req`.
+    pub fn of(req: &Request<'_>) -> Option<CacheDecision> {
+        *CacheDecision::cached(req).lock().expect("CacheDecision lock")
+    }
+
+    /// Records `self` as the decision for `req` unless a decision has already
+    /// been recorded.
+    pub(crate) fn record(self, req: &Request<'_>) {
+        let mut decision = CacheDecision::cached(req).lock().expect("CacheDecision lock");
+        if decision.is_none() {
+            *decision = Some(self);
+        }
+    }
+
+    /// Returns the request-local decision of `req`.
+    fn cached<'r>(req: &'r Request<'_>) -> &'r Mutex<Option<CacheDecision>> {
+        &req.local_cache(|| CachedDecision(Mutex::new(None))).0
+    }
+}
+
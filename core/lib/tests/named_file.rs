@@ -0,0 +1,1272 @@
+#![feature(proc_macro_hygiene)]
+
+#[macro_use] extern crate rocket;
+
+use std::path::{Path, PathBuf};
+
+use rocket::{Request, Data};
+use rocket::handler::Outcome;
+use rocket::response::NamedFile;
+
+fn manifest_path() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+}
+
+/// Returns the path of `name` in a temporary directory specific to this process.
+fn temp_path(name: &str) -> PathBuf {
+    let dir = format!("rocket-named-file-tests-{}", std::process::id());
+    let dir = std::env::temp_dir().join(dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[get("/regular")]
+fn regular() -> Option<NamedFile> {
+    NamedFile::open(manifest_path()).ok()
+}
+
+#[get("/device")]
+fn device() -> Option<NamedFile> {
+    NamedFile::open("/dev/null").ok()
+}
+
+#[get("/device/buffered")]
+fn buffered_device() -> Option<NamedFile> {
+    NamedFile::open("/dev/null").ok().map(|file| file.buffer_size(64 * 1024))
+}
+
+#[get("/procfs")]
+fn procfs() -> Option<NamedFile> {
+    NamedFile::open("/proc/self/status").ok()
+}
+
+fn replace<'r>(req: &'r Request<'_>, data: Data) -> Outcome<'r> {
+    let file = match NamedFile::open(manifest_path()) {
+        Ok(file) => file,
+        Err(_) => return Outcome::forward(data)
+    };
+
+    match file.check_preconditions(req) {
+        Some(status) => Outcome::failure(status),
+        None => Outcome::from(req, "replaced")
+    }
+}
+
+mod named_file_tests {
+    use super::*;
+
+    use std::fs;
+
+    use rocket::Rocket;
+    use rocket::local::Client;
+    use rocket::http::{Header, Status};
+    use rocket::response::Body;
+
+    fn rocket() -> Rocket {
+        rocket::ignite().mount("/", routes![regular, device, buffered_device, procfs])
+    }
+
+    #[test]
+    fn regular_file_is_sized() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.get("/regular").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let expected_len = fs::metadata(manifest_path()).unwrap().len();
+        match response.body().unwrap() {
+            Body::Sized(_, len) => assert_eq!(len, expected_len),
+            _ => panic!("expected a sized body")
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_regular_file_is_chunked() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.get("/device").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.body().unwrap().is_chunked());
+        assert_eq!(response.body_bytes(), Some(vec![]));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn buffer_size_is_chunk_size() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.get("/device").dispatch();
+        match response.body().unwrap() {
+            Body::Chunked(_, chunk_size) => assert_eq!(chunk_size, 8 * 1024),
+            _ => panic!("expected a chunked body")
+        }
+
+        let mut response = client.get("/device/buffered").dispatch();
+        match response.body().unwrap() {
+            Body::Chunked(_, chunk_size) => assert_eq!(chunk_size, 64 * 1024),
+            _ => panic!("expected a chunked body")
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn procfs_file_is_chunked() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.get("/procfs").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.body().unwrap().is_chunked());
+        assert!(response.body_string().unwrap().contains("Name:"));
+    }
+
+    #[test]
+    fn empty_file_is_sized() {
+        let path = temp_path("rocket-named-file-empty.txt");
+        fs::write(&path, "").unwrap();
+
+        #[get("/empty")]
+        fn empty() -> Option<NamedFile> {
+            NamedFile::open(temp_path("rocket-named-file-empty.txt")).ok()
+        }
+
+        let client = Client::new(rocket().mount("/", routes![empty])).unwrap();
+        let mut response = client.get("/empty").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("bytes"));
+        match response.body() {
+            Some(Body::Sized(_, len)) => assert_eq!(len, 0),
+            _ => panic!("expected a sized body")
+        }
+
+        assert_eq!(response.body_bytes(), Some(vec![]));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn empty_plain_file_is_sized() {
+        use std::fs::File;
+
+        let path = temp_path("rocket-plain-file-empty.txt");
+        fs::write(&path, "").unwrap();
+
+        #[get("/empty/plain")]
+        fn empty() -> Option<File> {
+            File::open(temp_path("rocket-plain-file-empty.txt")).ok()
+        }
+
+        let client = Client::new(rocket().mount("/", routes![empty])).unwrap();
+        let mut response = client.get("/empty/plain").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        match response.body() {
+            Some(Body::Sized(_, len)) => assert_eq!(len, 0),
+            _ => panic!("expected a sized body")
+        }
+
+        assert_eq!(response.body_bytes(), Some(vec![]));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn ranges_are_only_advertised_for_known_sizes() {
+        use rocket::response::MemoryFile;
+
+        #[get("/memory")]
+        fn memory() -> MemoryFile {
+            MemoryFile::new("hello.txt", &b"Hello, world!"[..])
+        }
+
+        let client = Client::new(rocket().mount("/", routes![memory])).unwrap();
+        let response = client.get("/memory").dispatch();
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("bytes"));
+
+        let response = client.get("/regular").dispatch();
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("bytes"));
+
+        // Range requests for files of unknown size receive the entire file.
+        let response = client.get("/device").header(Header::new("Range", "bytes=0-1")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("none"));
+        assert!(response.headers().get_one("Content-Range").is_none());
+    }
+}
+
+mod precondition_tests {
+    use super::*;
+
+    use rocket::{Rocket, Route};
+    use rocket::local::Client;
+    use rocket::http::{Header, Method, Status};
+
+    const EPOCH: &str = "Thu, 01 Jan 1970 00:00:00 GMT";
+    const FAR_FUTURE: &str = "Fri, 01 Jan 2999 00:00:00 GMT";
+
+    fn rocket() -> Rocket {
+        rocket::ignite()
+            .mount("/", routes![regular])
+            .mount("/", vec![Route::new(Method::Put, "/regular", replace)])
+    }
+
+    #[test]
+    fn validators_are_sent() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.get("/regular").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.headers().get_one("ETag").is_some());
+        assert!(response.headers().get_one("Last-Modified").is_some());
+    }
+
+    #[test]
+    fn unmodified_since_fails_for_newer_file() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.put("/regular")
+            .header(Header::new("If-Unmodified-Since", EPOCH))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PreconditionFailed);
+
+        let response = client.get("/regular")
+            .header(Header::new("If-Unmodified-Since", EPOCH))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PreconditionFailed);
+    }
+
+    #[test]
+    fn unmodified_since_passes_for_older_file() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.put("/regular")
+            .header(Header::new("If-Unmodified-Since", FAR_FUTURE))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("replaced".into()));
+    }
+
+    #[test]
+    fn if_match_uses_etag() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.get("/regular").dispatch();
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+
+        let response = client.put("/regular")
+            .header(Header::new("If-Match", etag))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.put("/regular")
+            .header(Header::new("If-Match", "\"some-other-tag\""))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PreconditionFailed);
+    }
+
+    #[test]
+    fn modification_time_is_truncated_to_seconds() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use rocket::response::MemoryFile;
+
+        #[get("/subsecond")]
+        fn subsecond() -> MemoryFile {
+            let modified = UNIX_EPOCH + Duration::from_millis(1_000_000_000_750);
+            MemoryFile::new("subsecond.txt", &b"Hello"[..]).modified(modified)
+        }
+
+        let client = Client::new(rocket::ignite().mount("/", routes![subsecond])).unwrap();
+        let response = client.get("/subsecond").dispatch();
+        let last_modified = "Sun, 09 Sep 2001 01:46:40 GMT";
+        assert_eq!(response.headers().get_one("Last-Modified"), Some(last_modified));
+
+        // The file was modified 750ms after the date sent to the client.
+        let response = client.get("/subsecond")
+            .header(Header::new("If-Modified-Since", last_modified))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        let response = client.get("/subsecond")
+            .header(Header::new("If-Unmodified-Since", last_modified))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/subsecond")
+            .header(Header::new("If-Modified-Since", "Sun, 09 Sep 2001 01:46:39 GMT"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn not_modified() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.get("/regular").dispatch();
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+        let last_modified = response.headers().get_one("Last-Modified").unwrap().to_string();
+
+        let mut response = client.get("/regular")
+            .header(Header::new("If-None-Match", etag))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+        assert!(response.body_bytes().is_none());
+
+        let response = client.get("/regular")
+            .header(Header::new("If-Modified-Since", last_modified))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        let response = client.get("/regular")
+            .header(Header::new("If-Modified-Since", EPOCH))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn etags_take_precedence_over_dates() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.get("/regular").dispatch();
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+        let last_modified = response.headers().get_one("Last-Modified").unwrap().to_string();
+
+        // A matching entity tag with a stale date is not modified.
+        let response = client.get("/regular")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .header(Header::new("If-Modified-Since", EPOCH))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        // A different entity tag with a fresh date is modified.
+        let mut response = client.get("/regular")
+            .header(Header::new("If-None-Match", "\"some-other-tag\""))
+            .header(Header::new("If-Modified-Since", last_modified))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.body_bytes().is_some());
+
+        // Likewise, `If-Unmodified-Since` is ignored in favor of `If-Match`.
+        let response = client.put("/regular")
+            .header(Header::new("If-Match", etag))
+            .header(Header::new("If-Unmodified-Since", EPOCH))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
+
+mod metadata_cache_tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use rocket::{Rocket, State};
+    use rocket::local::Client;
+    use rocket::http::Status;
+    use rocket::response::{NamedFile, MetadataCache};
+
+    fn test_file(name: &str) -> PathBuf {
+        let dir = format!("rocket-metadata-cache-{}", std::process::id());
+        let dir = std::env::temp_dir().join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(format!("{}.txt", name))
+    }
+
+    #[get("/cached/<name>")]
+    fn cached(name: String, cache: State<'_, MetadataCache>) -> Option<NamedFile> {
+        NamedFile::open_cached(test_file(&name), &cache).ok()
+    }
+
+    #[get("/uncached/<name>")]
+    fn uncached(name: String) -> Option<NamedFile> {
+        NamedFile::open(test_file(&name)).ok()
+    }
+
+    fn rocket(capacity: usize) -> Rocket {
+        rocket::ignite()
+            .manage(MetadataCache::new(capacity))
+            .mount("/", routes![cached, uncached])
+    }
+
+    fn append(name: &str, contents: &str) {
+        let mut file = OpenOptions::new().append(true).open(test_file(name)).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn cached_metadata_is_reused() {
+        fs::write(test_file("reused"), "Hello").unwrap();
+
+        let client = Client::new(rocket(4)).unwrap();
+        let mut response = client.get("/cached/reused").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("Hello".into()));
+        let etag = response.headers().get_one("ETag").map(|s| s.to_string());
+        let modified = response.headers().get_one("Last-Modified").map(|s| s.to_string());
+
+        let mut response = client.get("/cached/reused").dispatch();
+        assert_eq!(response.headers().get_one("ETag").map(|s| s.to_string()), etag);
+        assert_eq!(response.headers().get_one("Last-Modified").map(|s| s.to_string()), modified);
+        assert_eq!(response.body_string(), Some("Hello".into()));
+
+        // A change in length is noticed, so the entry is refreshed.
+        append("reused", ", world!");
+
+        let mut response = client.get("/cached/reused").dispatch();
+        assert_ne!(response.headers().get_one("ETag").map(|s| s.to_string()), etag);
+        assert_eq!(response.body_string(), Some("Hello, world!".into()));
+
+        fs::remove_file(test_file("reused")).unwrap();
+    }
+
+    #[test]
+    fn expired_metadata_is_refreshed() {
+        use std::cell::Cell;
+        use std::time::{Duration, Instant};
+
+        thread_local! {
+            static START: Instant = Instant::now();
+            static ELAPSED: Cell<Duration> = Cell::new(Duration::from_secs(0));
+        }
+
+        fn clock() -> Instant {
+            START.with(|start| *start + ELAPSED.with(|elapsed| elapsed.get()))
+        }
+
+        fs::write(test_file("expired"), "Hello").unwrap();
+        let cache = MetadataCache::new(4).ttl(Duration::from_secs(30)).clock(clock);
+        let file = NamedFile::open(test_file("expired")).unwrap().cached(&cache).unwrap();
+        assert_eq!(cache.validators(test_file("expired")), file.validators());
+
+        ELAPSED.with(|elapsed| elapsed.set(Duration::from_secs(29)));
+        assert_eq!(cache.validators(test_file("expired")), file.validators());
+
+        ELAPSED.with(|elapsed| elapsed.set(Duration::from_secs(30)));
+        assert!(cache.validators(test_file("expired")).is_none());
+
+        let file = NamedFile::open_cached(test_file("expired"), &cache).unwrap();
+        assert_eq!(cache.validators(test_file("expired")), file.validators());
+
+        fs::remove_file(test_file("expired")).unwrap();
+    }
+
+    #[test]
+    fn shrunk_files_are_refreshed() {
+        fs::write(test_file("shrunk"), "Hello, world!").unwrap();
+
+        let client = Client::new(rocket(4)).unwrap();
+        let mut response = client.get("/cached/shrunk").dispatch();
+        assert_eq!(response.body_string(), Some("Hello, world!".into()));
+        let etag = response.headers().get_one("ETag").map(|s| s.to_string());
+
+        // The cached length can't be sent anymore, so the metadata is reloaded.
+        fs::write(test_file("shrunk"), "Hello").unwrap();
+        let mut response = client.get("/cached/shrunk").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_ne!(response.headers().get_one("ETag").map(|s| s.to_string()), etag);
+        assert_eq!(response.body_string(), Some("Hello".into()));
+
+        fs::remove_file(test_file("shrunk")).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        fs::write(test_file("first"), "first").unwrap();
+        fs::write(test_file("second"), "second").unwrap();
+
+        let client = Client::new(rocket(1)).unwrap();
+        let mut response = client.get("/cached/first").dispatch();
+        assert_eq!(response.body_string(), Some("first".into()));
+
+        // Caching `second` evicts `first`, so its new length is seen.
+        append("first", ", changed");
+        let mut response = client.get("/cached/second").dispatch();
+        assert_eq!(response.body_string(), Some("second".into()));
+
+        let mut response = client.get("/cached/first").dispatch();
+        assert_eq!(response.body_string(), Some("first, changed".into()));
+
+        fs::remove_file(test_file("first")).unwrap();
+        fs::remove_file(test_file("second")).unwrap();
+    }
+}
+
+mod range_tests {
+    use std::io::Read;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use rocket::Rocket;
+    use rocket::local::Client;
+    use rocket::http::{Header, Status};
+    use rocket::response::{Body, MemoryFile};
+
+    #[get("/digits.txt")]
+    fn digits() -> MemoryFile {
+        MemoryFile::new("digits.txt", &b"0123456789"[..])
+    }
+
+    #[get("/dated.txt")]
+    fn dated() -> MemoryFile {
+        MemoryFile::new("dated.txt", &b"0123456789"[..])
+            .modified(UNIX_EPOCH + Duration::from_secs(1_000_000_000))
+    }
+
+    fn rocket() -> Rocket {
+        rocket::ignite().mount("/", routes![digits, dated])
+    }
+
+    fn assert_head_size(body: Option<Body<&mut dyn Read>>, expected: u64) {
+        match body {
+            Some(Body::Sized(body, size)) => {
+                assert_eq!(size, expected);
+                assert_eq!(body.bytes().count(), 0);
+            }
+            _ => panic!("expected a sized body")
+        }
+    }
+
+    fn get_range(client: &Client, range: &str) -> (Status, Option<String>, Option<String>) {
+        let mut response = client.get("/digits.txt")
+            .header(Header::new("Range", range.to_string()))
+            .dispatch();
+
+        let content_range = response.headers().get_one("Content-Range").map(|s| s.to_string());
+        (response.status(), content_range, response.body_string())
+    }
+
+    #[test]
+    fn full_response_accepts_ranges() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.get("/digits.txt").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Accept-Ranges"), Some("bytes"));
+        assert_eq!(response.body_string(), Some("0123456789".into()));
+    }
+
+    #[test]
+    fn single_ranges_are_honored() {
+        let client = Client::new(rocket()).unwrap();
+        let partial = |range: &str, body: &str| {
+            (Status::PartialContent, Some(range.to_string()), Some(body.to_string()))
+        };
+
+        assert_eq!(get_range(&client, "bytes=2-4"), partial("bytes 2-4/10", "234"));
+        assert_eq!(get_range(&client, "bytes=7-"), partial("bytes 7-9/10", "789"));
+        assert_eq!(get_range(&client, "bytes=-2"), partial("bytes 8-9/10", "89"));
+        assert_eq!(get_range(&client, "bytes=8-100"), partial("bytes 8-9/10", "89"));
+        assert_eq!(get_range(&client, "bytes=-100"), partial("bytes 0-9/10", "0123456789"));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_rejected() {
+        let client = Client::new(rocket()).unwrap();
+        for range in &["bytes=10-", "bytes=-0", "bytes=10-11,12-"] {
+            let (status, content_range, _) = get_range(&client, range);
+            assert_eq!(status, Status::RangeNotSatisfiable);
+            assert_eq!(content_range, Some("bytes */10".into()));
+        }
+    }
+
+    #[test]
+    fn other_ranges_are_ignored() {
+        let client = Client::new(rocket()).unwrap();
+        for range in &["bytes=4-2", "bytes=a-b", "items=0-1", "bytes=0-1,a-b"] {
+            let (status, content_range, body) = get_range(&client, range);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(content_range, None);
+            assert_eq!(body, Some("0123456789".into()));
+        }
+    }
+
+    #[test]
+    fn multiple_ranges_are_multipart() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.get("/digits.txt")
+            .header(Header::new("Range", "bytes=0-1, 4-5, -1"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.headers().get_one("Content-Range"), None);
+
+        let content_type = response.content_type().unwrap();
+        assert_eq!(content_type.top(), "multipart");
+        assert_eq!(content_type.sub(), "byteranges");
+        let boundary = content_type.params()
+            .find(|&(name, _)| name == "boundary")
+            .map(|(_, value)| value.to_string())
+            .unwrap();
+
+        let len = response.body().map(|body| match body {
+            Body::Sized(_, len) => len,
+            _ => panic!("expected a sized body")
+        });
+
+        let body = response.body_string().unwrap();
+        assert_eq!(len, Some(body.len() as u64));
+
+        let delimiter = format!("--{}", boundary);
+        let parts: Vec<_> = body.split(&*delimiter).collect();
+        assert_eq!(parts.len(), 5);
+        assert_eq!(parts[0], "");
+        assert_eq!(parts[4], "--\r\n");
+
+        let expected = [("0-1", "01"), ("4-5", "45"), ("9-9", "9")];
+        for (part, (range, data)) in parts[1..4].iter().zip(expected.iter()) {
+            let expected = format!("\r\nContent-Type: text/plain; charset=utf-8\r\n\
+                Content-Range: bytes {}/10\r\n\r\n{}\r\n", range, data);
+            assert_eq!(*part, expected);
+        }
+    }
+
+    #[test]
+    fn too_many_ranges_are_ignored() {
+        let client = Client::new(rocket()).unwrap();
+        let ranges: Vec<_> = (0..17).map(|_| "0-0").collect();
+        let range = format!("bytes={}", ranges.join(","));
+        let (status, content_range, body) = get_range(&client, &range);
+        assert_eq!(status, Status::Ok);
+        assert_eq!(content_range, None);
+        assert_eq!(body, Some("0123456789".into()));
+    }
+
+    #[test]
+    fn head_with_range() {
+        let client = Client::new(rocket()).unwrap();
+        let mut response = client.head("/digits.txt")
+            .header(Header::new("Range", "bytes=2-4"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.headers().get_one("Content-Range"), Some("bytes 2-4/10"));
+        assert_head_size(response.body(), 3);
+    }
+
+    #[test]
+    fn head_with_if_none_match() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.head("/dated.txt").dispatch();
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+
+        let mut response = client.head("/dated.txt")
+            .header(Header::new("If-None-Match", etag))
+            .header(Header::new("Range", "bytes=2-4"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+        assert_eq!(response.headers().get_one("Content-Range"), None);
+        assert!(response.body().is_none());
+    }
+
+    #[test]
+    fn head_with_range_and_if_range() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.head("/dated.txt").dispatch();
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+        let last_modified = response.headers().get_one("Last-Modified").unwrap().to_string();
+
+        for if_range in &[etag, last_modified] {
+            let mut response = client.head("/dated.txt")
+                .header(Header::new("Range", "bytes=-4"))
+                .header(Header::new("If-Range", if_range.clone()))
+                .dispatch();
+
+            assert_eq!(response.status(), Status::PartialContent);
+            assert_eq!(response.headers().get_one("Content-Range"), Some("bytes 6-9/10"));
+            assert_head_size(response.body(), 4);
+        }
+
+        let mut response = client.head("/dated.txt")
+            .header(Header::new("Range", "bytes=-4"))
+            .header(Header::new("If-Range", "\"stale\""))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Content-Range"), None);
+        assert_head_size(response.body(), 10);
+    }
+}
+
+mod open_memory_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rocket::{Rocket, State};
+    use rocket::local::Client;
+    use rocket::http::{Header, Status};
+    use rocket::response::{NamedFile, MemoryFile};
+
+    fn test_file() -> PathBuf {
+        super::temp_path("rocket-open-memory.txt")
+    }
+
+    #[get("/memory")]
+    fn memory(file: State<'_, MemoryFile>) -> MemoryFile {
+        file.clone()
+    }
+
+    fn rocket() -> Rocket {
+        let file = NamedFile::open_memory(test_file()).unwrap();
+        rocket::ignite().manage(file).mount("/", routes![memory])
+    }
+
+    #[test]
+    fn responds_from_memory() {
+        fs::write(test_file(), "Hello, world!").unwrap();
+        let client = Client::new(rocket()).unwrap();
+
+        // Neither changing nor removing the file affects the response.
+        fs::write(test_file(), "Goodbye!").unwrap();
+        let mut response = client.get("/memory").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("Hello, world!".into()));
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+
+        fs::remove_file(test_file()).unwrap();
+        let mut response = client.get("/memory").dispatch();
+        assert_eq!(response.headers().get_one("ETag"), Some(&*etag));
+        assert_eq!(response.body_string(), Some("Hello, world!".into()));
+
+        let response = client.get("/memory")
+            .header(Header::new("If-None-Match", etag))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+
+        let mut response = client.get("/memory")
+            .header(Header::new("Range", "bytes=7-11"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.body_string(), Some("world".into()));
+    }
+}
+
+mod download_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rocket::local::Client;
+    use rocket::http::{ContentType, Status};
+    use rocket::response::NamedFile;
+
+    fn stored_file() -> PathBuf {
+        super::temp_path("rocket-download-3f1c9a52-8d4e-4b7a-9e61-0c2d5b8f7a13")
+    }
+
+    #[get("/report")]
+    fn report() -> Option<NamedFile> {
+        NamedFile::open(stored_file()).ok().map(|file| file.download_as("report.pdf"))
+    }
+
+    #[get("/resume")]
+    fn resume() -> Option<NamedFile> {
+        NamedFile::open(stored_file()).ok().map(|file| file.download_as("résumé \"final\".pdf"))
+    }
+
+    #[test]
+    fn download_name_is_used() {
+        fs::write(stored_file(), "%PDF-1.4").unwrap();
+
+        let client = Client::new(rocket::ignite().mount("/", routes![report, resume])).unwrap();
+        let mut response = client.get("/report").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::PDF));
+        assert_eq!(response.headers().get_one("Content-Disposition"),
+            Some("attachment; filename=\"report.pdf\""));
+        assert_eq!(response.body_string(), Some("%PDF-1.4".into()));
+
+        let response = client.get("/resume").dispatch();
+        assert_eq!(response.headers().get_one("Content-Disposition"),
+            Some("attachment; filename=\"r_sum_ \\\"final\\\".pdf\"; \
+                filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.pdf"));
+
+        fs::remove_file(stored_file()).unwrap();
+    }
+
+    fn disposition_file() -> PathBuf {
+        super::temp_path("rocket-disposition-report.pdf")
+    }
+
+    #[get("/plain")]
+    fn plain() -> Option<NamedFile> {
+        NamedFile::open(disposition_file()).ok()
+    }
+
+    #[get("/inline")]
+    fn inline() -> Option<NamedFile> {
+        NamedFile::open(disposition_file()).ok().map(|file| file.inline())
+    }
+
+    #[get("/attachment")]
+    fn attachment() -> Option<NamedFile> {
+        NamedFile::open(disposition_file()).ok().map(|file| file.attachment())
+    }
+
+    #[get("/inline_named")]
+    fn inline_named() -> Option<NamedFile> {
+        NamedFile::open(disposition_file()).ok().map(|file| file.download_as("q1.pdf").inline())
+    }
+
+    #[test]
+    fn disposition_is_used() {
+        fs::write(disposition_file(), "%PDF-1.4").unwrap();
+
+        let routes = routes![plain, inline, attachment, inline_named];
+        let client = Client::new(rocket::ignite().mount("/", routes)).unwrap();
+        let response = client.get("/plain").dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::PDF));
+        assert!(response.headers().get_one("Content-Disposition").is_none());
+
+        let response = client.get("/inline").dispatch();
+        assert_eq!(response.headers().get_one("Content-Disposition"), Some("inline"));
+
+        let response = client.get("/attachment").dispatch();
+        assert_eq!(response.headers().get_one("Content-Disposition"), Some("attachment"));
+
+        let response = client.get("/inline_named").dispatch();
+        assert_eq!(response.headers().get_one("Content-Disposition"),
+            Some("inline; filename=\"q1.pdf\""));
+
+        fs::remove_file(disposition_file()).unwrap();
+    }
+}
+
+mod open_in_root_tests {
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    use rocket::response::NamedFile;
+
+    fn root() -> PathBuf {
+        super::temp_path("rocket-open-in-root").join("static")
+    }
+
+    #[test]
+    fn opens_files_inside_root() {
+        fs::create_dir_all(root().join("css")).unwrap();
+        fs::write(root().join("css/app.css"), "body {}").unwrap();
+        fs::write(root().join("../secret.txt"), "secret").unwrap();
+
+        let file = NamedFile::open_in_root(root(), "css/app.css").unwrap();
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "body {}");
+        assert!(NamedFile::open_in_root(root(), "css/../css/app.css").is_ok());
+
+        let escapes = &["../secret.txt", "css/../../secret.txt", "../../etc/passwd"];
+        for path in escapes {
+            let error = NamedFile::open_in_root(root(), path).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::NotFound);
+        }
+
+        let error = NamedFile::open_in_root(root(), "missing.css").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
+
+#[cfg(unix)]
+mod open_timeout_tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    use rocket::response::NamedFile;
+
+    fn dir() -> PathBuf {
+        super::temp_path("rocket-open-timeout")
+    }
+
+    #[test]
+    fn opens_files_within_timeout() {
+        fs::create_dir_all(dir()).unwrap();
+        fs::write(dir().join("file.txt"), "contents").unwrap();
+
+        let file = NamedFile::open_timeout(dir().join("file.txt"), Duration::from_secs(5));
+        assert_eq!(fs::read_to_string(file.unwrap().path()).unwrap(), "contents");
+
+        let timeout = Duration::from_secs(5);
+        let error = NamedFile::open_timeout(dir().join("missing.txt"), timeout).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn times_out_on_slow_opens() {
+        // Opening a FIFO for reading blocks until a writer opens it, which
+        // stands in for a file system that never responds.
+        let fifo = dir().join("slow.fifo");
+        fs::create_dir_all(dir()).unwrap();
+        let _ = fs::remove_file(&fifo);
+        let status = Command::new("mkfifo").arg(&fifo).status().expect("mkfifo");
+        assert!(status.success());
+
+        let (start, timeout) = (Instant::now(), Duration::from_millis(100));
+        let error = NamedFile::open_timeout(&fifo, timeout).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() >= timeout);
+
+        // Unblock the opening thread.
+        OpenOptions::new().write(true).open(&fifo).unwrap();
+        fs::remove_file(&fifo).unwrap();
+    }
+}
+
+mod sniff_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rocket::local::Client;
+    use rocket::http::{ContentType, Status};
+    use rocket::response::NamedFile;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    fn stored_file(name: &str) -> PathBuf {
+        super::temp_path("rocket-sniff").join(name)
+    }
+
+    #[get("/sniffed/<name>")]
+    fn sniffed(name: String) -> Option<NamedFile> {
+        NamedFile::open(stored_file(&name)).ok().map(|file| file.sniff_content_type())
+    }
+
+    #[get("/plain/<name>")]
+    fn plain(name: String) -> Option<NamedFile> {
+        NamedFile::open(stored_file(&name)).ok()
+    }
+
+    #[test]
+    fn sniffs_extensionless_files() {
+        fs::create_dir_all(stored_file("")).unwrap();
+        fs::write(stored_file("upload"), PNG).unwrap();
+        fs::write(stored_file("upload.txt"), PNG).unwrap();
+        fs::write(stored_file("notes"), "just some text").unwrap();
+
+        let client = Client::new(rocket::ignite().mount("/", routes![sniffed, plain])).unwrap();
+        let mut response = client.get("/sniffed/upload").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::PNG));
+        assert_eq!(response.body_bytes(), Some(PNG.to_vec()));
+
+        // A recognized extension takes precedence over the file's contents.
+        let response = client.get("/sniffed/upload.txt").dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::Plain));
+
+        let mut response = client.get("/sniffed/notes").dispatch();
+        assert_eq!(response.content_type(), None);
+        assert_eq!(response.body_string(), Some("just some text".into()));
+
+        let response = client.get("/plain/upload").dispatch();
+        assert_eq!(response.content_type(), None);
+
+        fs::remove_dir_all(stored_file("")).unwrap();
+    }
+}
+
+mod negotiation_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rocket::local::Client;
+    use rocket::http::{Accept, ContentType, Header, Status};
+    use rocket::response::NegotiatedFile;
+
+    fn base() -> PathBuf {
+        super::temp_path("rocket-negotiated-data")
+    }
+
+    #[get("/data")]
+    fn data() -> NegotiatedFile {
+        NegotiatedFile::new(base(), &["html", "json"])
+    }
+
+    #[test]
+    fn file_is_chosen_by_accept() {
+        fs::write(base().with_extension("html"), "<p>data</p>").unwrap();
+        fs::write(base().with_extension("json"), "{\"data\": true}").unwrap();
+
+        let client = Client::new(rocket::ignite().mount("/", routes![data])).unwrap();
+        let cases: &[(Option<&str>, ContentType, &str)] = &[
+            (Some("application/json"), ContentType::JSON, "{\"data\": true}"),
+            (Some("text/html"), ContentType::HTML, "<p>data</p>"),
+            (Some("text/html;q=0.5, application/json"), ContentType::JSON, "{\"data\": true}"),
+            (Some("application/*"), ContentType::JSON, "{\"data\": true}"),
+            (Some("image/png"), ContentType::HTML, "<p>data</p>"),
+            (None, ContentType::HTML, "<p>data</p>"),
+        ];
+
+        for (accept, content_type, body) in cases {
+            let mut request = client.get("/data");
+            if let Some(accept) = accept {
+                request.add_header(accept.parse::<Accept>().unwrap());
+            }
+
+            let mut response = request.dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.content_type().as_ref(), Some(content_type));
+            assert_eq!(response.headers().get_one("Vary"), Some("Accept"));
+            assert_eq!(response.body_string(), Some(body.to_string()));
+        }
+
+        fs::remove_file(base().with_extension("json")).unwrap();
+        let response = client.get("/data").header(Header::new("Accept", "application/json")).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        fs::remove_file(base().with_extension("html")).unwrap();
+    }
+}
+
+mod cache_decision_tests {
+    use super::*;
+
+    use rocket::Rocket;
+    use rocket::fairing::AdHoc;
+    use rocket::local::Client;
+    use rocket::http::{Header, Status};
+    use rocket::response::CacheDecision;
+
+    #[get("/plain")]
+    fn plain() -> &'static str {
+        "not a file"
+    }
+
+    fn rocket() -> Rocket {
+        rocket::ignite()
+            .mount("/", routes![regular, plain])
+            .attach(AdHoc::on_response("Cache Decision", |req, res| {
+                let decision = format!("{:?}", CacheDecision::of(req));
+                res.set_raw_header("X-Cache-Decision", decision);
+            }))
+    }
+
+    #[test]
+    fn decision_is_recorded() {
+        let client = Client::new(rocket()).unwrap();
+        let response = client.get("/regular").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("X-Cache-Decision"), Some("Some(Sent)"));
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+
+        let response = client.get("/regular")
+            .header(Header::new("If-None-Match", etag))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotModified);
+        assert_eq!(response.headers().get_one("X-Cache-Decision"), Some("Some(NotModified)"));
+
+        let response = client.get("/regular")
+            .header(Header::new("If-Match", "\"some-other-tag\""))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PreconditionFailed);
+        assert_eq!(response.headers().get_one("X-Cache-Decision"),
+            Some("Some(PreconditionFailed)"));
+
+        let response = client.get("/plain").dispatch();
+        assert_eq!(response.headers().get_one("X-Cache-Decision"), Some("None"));
+    }
+
+    #[test]
+    fn decision_is_recorded_after_early_lookup() {
+        let rocket = rocket().attach(AdHoc::on_request("Early Lookup", |req, _| {
+            assert_eq!(CacheDecision::of(req), None);
+        }));
+
+        let client = Client::new(rocket).unwrap();
+        let response = client.get("/regular").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("X-Cache-Decision"), Some("Some(Sent)"));
+    }
+}
+
+mod status_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rocket::local::Client;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::response::NamedFile;
+
+    fn error_page() -> PathBuf {
+        super::temp_path("rocket-status-404.html")
+    }
+
+    #[get("/missing")]
+    fn missing() -> Option<NamedFile> {
+        NamedFile::open(error_page()).ok().map(|file| file.with_status(Status::NotFound))
+    }
+
+    #[test]
+    fn file_is_served_with_status() {
+        fs::write(error_page(), "<h1>Not Found</h1>").unwrap();
+        let client = Client::new(rocket::ignite().mount("/", routes![missing])).unwrap();
+
+        let mut response = client.get("/missing").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        assert_eq!(response.headers().get_one("ETag"), None);
+        assert_eq!(response.body_string(), Some("<h1>Not Found</h1>".into()));
+
+        let mut response = client.get("/missing")
+            .header(Header::new("Range", "bytes=0-1"))
+            .header(Header::new("If-Modified-Since", "Fri, 01 Jan 2999 00:00:00 GMT"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.body_string(), Some("<h1>Not Found</h1>".into()));
+
+        fs::remove_file(error_page()).unwrap();
+    }
+}
+
+mod file_slice_tests {
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+
+    use rocket::local::Client;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::response::{Body, NamedFile, FileSlice};
+
+    fn pack_file() -> PathBuf {
+        super::temp_path("rocket-file-slice.pack")
+    }
+
+    #[get("/entry")]
+    fn entry() -> Option<FileSlice> {
+        let file = File::open(pack_file()).ok()?;
+        Some(NamedFile::from_file_range(file, 6, 5, ContentType::Plain))
+    }
+
+    #[test]
+    fn slice_is_served() {
+        fs::write(pack_file(), "Hello world and more").unwrap();
+        let client = Client::new(rocket::ignite().mount("/", routes![entry])).unwrap();
+
+        let mut response = client.get("/entry").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::Plain));
+        match response.body().unwrap() {
+            Body::Sized(_, len) => assert_eq!(len, 5),
+            _ => panic!("expected a sized body")
+        }
+
+        assert_eq!(response.body_string(), Some("world".into()));
+
+        let mut response = client.get("/entry")
+            .header(Header::new("Range", "bytes=1-2"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(response.headers().get_one("Content-Range"), Some("bytes 1-2/5"));
+        assert_eq!(response.body_string(), Some("or".into()));
+
+        fs::remove_file(pack_file()).unwrap();
+    }
+}
+
+mod metered_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rocket::local::Client;
+    use rocket::http::{Header, Status};
+    use rocket::response::{Metered, NamedFile};
+
+    use super::manifest_path;
+
+    static SENT: AtomicUsize = AtomicUsize::new(usize::max_value());
+
+    #[get("/metered")]
+    fn metered() -> Option<Metered<NamedFile>> {
+        let file = NamedFile::open(manifest_path()).ok()?;
+        Some(Metered::new(file, |sent| SENT.store(sent as usize, Ordering::SeqCst)))
+    }
+
+    #[test]
+    fn reports_bytes_sent() {
+        let client = Client::new(rocket::ignite().mount("/", routes![metered])).unwrap();
+        let mut response = client.get("/metered")
+            .header(Header::new("Range", "bytes=10-19"))
+            .dispatch();
+
+        assert_eq!(response.status(), Status::PartialContent);
+        assert_eq!(SENT.load(Ordering::SeqCst), usize::max_value());
+        assert_eq!(response.body_bytes().map(|body| body.len()), Some(10));
+        assert_eq!(SENT.load(Ordering::SeqCst), 10);
+
+        // The body of a response to a `HEAD` request is never sent.
+        let response = client.head("/metered").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(SENT.load(Ordering::SeqCst), 0);
+
+        let len = std::fs::metadata(manifest_path()).unwrap().len();
+        let mut response = client.get("/metered").dispatch();
+        response.body_bytes();
+        assert_eq!(SENT.load(Ordering::SeqCst) as u64, len);
+    }
+}
+
+mod last_modified_tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use rocket::local::Client;
+    use rocket::response::{Response, Validators};
+
+    use super::manifest_path;
+
+    #[get("/regular")]
+    fn regular() -> Option<rocket::response::NamedFile> {
+        rocket::response::NamedFile::open(manifest_path()).ok()
+    }
+
+    fn last_modified(modified: SystemTime) -> Option<String> {
+        let mut response = Response::new();
+        Validators::new(0, modified).set_headers(&mut response);
+        assert!(response.headers().get_one("ETag").is_some());
+        response.headers().get_one("Last-Modified").map(String::from)
+    }
+
+    #[test]
+    fn file_has_last_modified() {
+        let client = Client::new(rocket::ignite().mount("/", routes![regular])).unwrap();
+        let response = client.get("/regular").dispatch();
+        let header = response.headers().get_one("Last-Modified").expect("Last-Modified");
+        assert!(header.ends_with(" GMT"));
+    }
+
+    #[test]
+    fn times_near_the_epoch() {
+        let epoch = Some("Thu, 01 Jan 1970 00:00:00 GMT".to_string());
+        assert_eq!(last_modified(UNIX_EPOCH), epoch);
+        assert_eq!(last_modified(UNIX_EPOCH + Duration::from_nanos(1)), epoch);
+        assert_eq!(last_modified(UNIX_EPOCH - Duration::from_nanos(1)), epoch);
+        assert_eq!(last_modified(UNIX_EPOCH + Duration::from_millis(999)), epoch);
+        assert_eq!(last_modified(UNIX_EPOCH + Duration::from_secs(1)),
+            Some("Thu, 01 Jan 1970 00:00:01 GMT".into()));
+        assert_eq!(last_modified(UNIX_EPOCH - Duration::from_secs(1)),
+            Some("Wed, 31 Dec 1969 23:59:59 GMT".into()));
+    }
+
+    #[test]
+    fn odd_times_never_panic() {
+        let mut secs: u64 = 1;
+        while secs < 1 << 40 {
+            for &offset in &[0, 1, 59, 3599, 86_399] {
+                let duration = Duration::from_secs(secs + offset);
+                for &time in &[UNIX_EPOCH.checked_add(duration), UNIX_EPOCH.checked_sub(duration)] {
+                    if let Some(time) = time {
+                        let value = last_modified(time);
+                        assert!(value.map_or(true, |value| value.ends_with(" GMT")));
+                    }
+                }
+            }
+
+            secs = secs * 3 + 1;
+        }
+
+        // Dates after the year 9999 can't be expressed as HTTP dates.
+        assert_eq!(last_modified(UNIX_EPOCH + Duration::from_secs(300_000_000_000)), None);
+        assert_eq!(last_modified(UNIX_EPOCH + Duration::from_secs(253_402_300_799)),
+            Some("Fri, 31 Dec 9999 23:59:59 GMT".into()));
+    }
+}